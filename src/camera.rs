@@ -0,0 +1,213 @@
+use bevy::prelude::*;
+
+use crate::{
+    fighter::FighterStateUpdate,
+    fighter_state::FighterState,
+    projectile::Projectile,
+    stage::{CurrentStage, StageDescription},
+    utils::FrameNumber,
+    view::ViewSet,
+};
+
+// World units kept around the tracked objects
+const CAMERA_PADDING: Vec2 = Vec2::new(200.0, 150.0);
+// Smallest area the camera will zoom in to
+const CAMERA_MIN_VIEW_SIZE: Vec2 = Vec2::new(600.0, 400.0);
+// Fraction of the distance to the target covered each frame
+const CAMERA_SMOOTHING: f32 = 0.1;
+// Hits with at least this much hitstun shake the camera
+const CAMERA_SHAKE_HITSTUN_THRESHOLD: FrameNumber = 40;
+const CAMERA_SHAKE_DURATION_FRAMES: FrameNumber = 12;
+const CAMERA_SHAKE_MAGNITUDE: f32 = 8.0;
+
+// Where the camera should be and how far it should be zoomed out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Framing {
+    pub centre: Vec2,
+    pub scale: f32,
+}
+
+// Frames every point with padding, without showing anything outside of `bounds`.
+// `viewport` is the size of the camera's view at a scale of 1
+pub fn frame_points(
+    points: impl IntoIterator<Item = Vec2>,
+    viewport: Vec2,
+    bounds: Option<Rect>,
+) -> Option<Framing> {
+    let mut points = points.into_iter();
+    let first = points.next()?;
+    let targets = points.fold(Rect::from_center_size(first, Vec2::ZERO), |rect, point| {
+        rect.union_point(point)
+    });
+    let size = (targets.size() + 2.0 * CAMERA_PADDING).max(CAMERA_MIN_VIEW_SIZE);
+    let mut scale = (size / viewport).max_element();
+    if let Some(bounds) = bounds {
+        // Never zoom out further than the bounds allow
+        scale = scale.min((bounds.size() / viewport).min_element());
+    }
+    let centre = match bounds {
+        Some(bounds) => clamp_centre(targets.center(), viewport * scale, bounds),
+        None => targets.center(),
+    };
+    Some(Framing { centre, scale })
+}
+
+// Keeps a view of the given size within the bounds
+pub fn clamp_centre(centre: Vec2, view_size: Vec2, bounds: Rect) -> Vec2 {
+    let half_view = (0.5 * view_size).min(0.5 * bounds.size());
+    let min = bounds.min + half_view;
+    // Rounding could otherwise put the upper limit just below the lower one
+    let max = (bounds.max - half_view).max(min);
+    centre.clamp(min, max)
+}
+
+pub fn smooth_framing(current: Framing, target: Framing, smoothing: f32) -> Framing {
+    Framing {
+        centre: current.centre.lerp(target.centre, smoothing),
+        scale: current.scale + (target.scale - current.scale) * smoothing,
+    }
+}
+
+// Offset of a shake with the given number of frames left, fading out over time
+pub fn shake_offset(remaining: FrameNumber, duration: FrameNumber, magnitude: f32) -> Vec2 {
+    if duration == 0 {
+        return Vec2::ZERO;
+    }
+    let strength = magnitude * remaining as f32 / duration as f32;
+    let phase = remaining as f32;
+    Vec2::new((phase * 2.3).sin(), (phase * 3.7).cos()) * strength
+}
+
+#[derive(Component)]
+pub struct GameCamera {
+    // Framing before any shake is applied
+    framing: Framing,
+    shake_frames: FrameNumber,
+}
+
+impl Default for GameCamera {
+    fn default() -> Self {
+        GameCamera {
+            framing: Framing {
+                centre: Vec2::ZERO,
+                scale: 1.0,
+            },
+            shake_frames: 0,
+        }
+    }
+}
+
+fn frame_fighters(
+    mut q_camera: Query<(&mut GameCamera, &mut Transform, &mut OrthographicProjection)>,
+    q_fighters: Query<&GlobalTransform, With<FighterState>>,
+    q_projectiles: Query<&GlobalTransform, With<Projectile>>,
+    stage: Option<Res<CurrentStage>>,
+    stages: Res<Assets<StageDescription>>,
+) {
+    let bounds = stage
+        .and_then(|stage| stages.get(&stage.handle))
+        .map(|stage| stage.camera_bounds);
+    let points: Vec<Vec2> = q_fighters
+        .iter()
+        .chain(q_projectiles.iter())
+        .map(|transform| transform.translation().xy())
+        .collect();
+    for (mut game_camera, mut transform, mut projection) in q_camera.iter_mut() {
+        // The projection's area is already scaled by the current zoom
+        let viewport = projection.area.size() / projection.scale;
+        if viewport.min_element() <= 0.0 {
+            continue;
+        }
+        let Some(target) = frame_points(points.iter().copied(), viewport, bounds) else {
+            continue;
+        };
+        let framing = smooth_framing(game_camera.framing, target, CAMERA_SMOOTHING);
+        game_camera.framing = framing;
+        game_camera.shake_frames = game_camera.shake_frames.saturating_sub(1);
+        let shake = shake_offset(
+            game_camera.shake_frames,
+            CAMERA_SHAKE_DURATION_FRAMES,
+            CAMERA_SHAKE_MAGNITUDE,
+        );
+        transform.translation.x = framing.centre.x + shake.x;
+        transform.translation.y = framing.centre.y + shake.y;
+        projection.scale = framing.scale;
+    }
+}
+
+fn shake_on_strong_hits(
+    mut q_camera: Query<&mut GameCamera>,
+    mut ev_state: EventReader<FighterStateUpdate>,
+) {
+    let strong_hits = ev_state
+        .read()
+        .filter(|update| {
+            matches!(
+                update.1,
+                FighterState::Hitstun(frames) if frames >= CAMERA_SHAKE_HITSTUN_THRESHOLD
+            )
+        })
+        .count();
+    if strong_hits == 0 {
+        return;
+    }
+    for mut camera in q_camera.iter_mut() {
+        camera.shake_frames = CAMERA_SHAKE_DURATION_FRAMES;
+    }
+}
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (shake_on_strong_hits, frame_fighters)
+                .chain()
+                .in_set(ViewSet),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEWPORT: Vec2 = Vec2::new(1200.0, 800.0);
+
+    #[test]
+    fn no_points_no_framing() {
+        assert_eq!(frame_points(std::iter::empty(), VIEWPORT, None), None);
+    }
+
+    #[test]
+    fn close_points_use_minimum_size() {
+        let framing = frame_points([Vec2::new(10.0, 20.0)], VIEWPORT, None).unwrap();
+        assert_eq!(framing.centre, Vec2::new(10.0, 20.0));
+        assert_eq!(VIEWPORT * framing.scale, CAMERA_MIN_VIEW_SIZE);
+    }
+
+    #[test]
+    fn distant_points_are_padded() {
+        let points = [Vec2::new(-1000.0, 0.0), Vec2::new(1000.0, 0.0)];
+        let framing = frame_points(points, VIEWPORT, None).unwrap();
+        assert_eq!(framing.centre, Vec2::ZERO);
+        assert_eq!(VIEWPORT.x * framing.scale, 2000.0 + 2.0 * CAMERA_PADDING.x);
+    }
+
+    #[test]
+    fn framing_stays_within_bounds() {
+        let bounds = Rect::new(-700.0, -500.0, 700.0, 500.0);
+        let points = [Vec2::new(600.0, 0.0), Vec2::new(3000.0, 0.0)];
+        let framing = frame_points(points, VIEWPORT, Some(bounds)).unwrap();
+        let view = Rect::from_center_size(framing.centre, VIEWPORT * framing.scale);
+        assert!(bounds.contains(view.min) && bounds.contains(view.max));
+    }
+
+    #[test]
+    fn view_larger_than_bounds_is_centred() {
+        let bounds = Rect::new(0.1, 0.2, 100.3, 50.7);
+        let centre = clamp_centre(Vec2::new(500.0, -500.0), Vec2::new(400.0, 300.0), bounds);
+        assert!(centre.abs_diff_eq(bounds.center(), 1e-4));
+    }
+}
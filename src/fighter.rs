@@ -1,17 +1,20 @@
-use std::f32::consts::PI;
+use std::collections::HashSet;
 
 use bevy::prelude::*;
 
 use crate::{
     fighter_state::{
-        apply_state_transition, FighterState, FighterStateTransition, AIRDODGE_DURATION_FRAMES,
-        AIRDODGE_INITIAL_SPEED, DEFAULT_JUMP_SQUAT_DURATION, RUN_TURNAROUND_DURATION_FRAMES,
+        apply_state_transition, hitstun_frames, FighterState, FighterStateTransition, StateArmour,
+        AIRDODGE_DURATION_FRAMES, AIRDODGE_INITIAL_SPEED, DEFAULT_JUMP_SQUAT_DURATION,
+        JAB_RESET_WINDOW_FRAMES, RUN_TURNAROUND_DURATION_FRAMES, STAGE_SPIKE_FREEZE_FRAMES,
         TURNAROUND_DURATION_FRAMES,
     },
-    hitbox::{HitboxCollision, HitboxPurpose, KnockbackAngle},
-    input::{Action, Control},
-    physics::{Collision, Gravity, SetVelocity, Velocity},
-    utils::{Directed, FrameCount, FrameNumber},
+    hitbox::{knockback_side, launch, HitboxCollision, HitboxPurpose, Launch},
+    input::{Action, BufferedInput, Control, DirectionalAction},
+    physics::{
+        Collision, DropThrough, Gravity, MaxFallSpeed, Platform, Pushbox, SetVelocity, Velocity,
+    },
+    utils::{CardinalDirection, Directed, FrameCount, FrameNumber, LeftRight},
     Airborne, AnimationIndices, AnimationTimer, Facing, PhysicsSet,
 };
 
@@ -19,6 +22,19 @@ pub mod megaman;
 
 // Control thresholds
 pub const CROUCH_THRESHOLD: f32 = 0.4;
+pub const PLATFORM_DROP_THRESHOLD: f32 = 0.8;
+
+const FAST_FALL_SPEED_MULTIPLIER: f32 = 2.0;
+
+// Hitting a surface faster than this while in hitstun causes a stage spike (see GDD)
+const STAGE_SPIKE_SPEED_THRESHOLD: f32 = 8.0;
+// Shield must be pressed within this many frames before hitting a surface to tech
+const TECH_WINDOW_FRAMES: FrameNumber = 20;
+// Prevents mashing shield to tech
+const TECH_LOCKOUT_FRAMES: FrameNumber = 40;
+const ROLL_SPEED: f32 = 4.0;
+// Hits on knocked down fighters weaker than this jab reset them instead of launching them
+const JAB_RESET_LAUNCH_SPEED_THRESHOLD: f32 = 4.0;
 
 #[derive(Component)]
 pub struct PlayerId(pub usize);
@@ -48,7 +64,7 @@ impl Default for FighterProperties {
 }
 
 #[derive(Event)]
-pub struct FighterStateUpdate(Entity, FighterState);
+pub struct FighterStateUpdate(pub Entity, pub FighterState);
 
 fn update_fighter_state(
     mut updates: EventReader<FighterStateUpdate>,
@@ -116,6 +132,35 @@ fn set_airdodge_speed(mut query: Query<(&FighterState, &FrameCount, &mut Velocit
     }
 }
 
+#[derive(Component)]
+pub struct WallJumpSpeed(pub f32);
+
+fn apply_wall_jump_speed(
+    mut query: Query<(
+        &FighterState,
+        &FrameCount,
+        &mut Facing,
+        &mut Velocity,
+        &JumpSpeed,
+        &WallJumpSpeed,
+    )>,
+) {
+    for (state, frame, mut facing, mut velocity, jump_speed, wall_jump_speed) in query.iter_mut() {
+        let FighterState::WallJump(wall_side) = state else {
+            continue;
+        };
+        if frame.0 != 0 {
+            continue;
+        }
+        let away_from_wall = wall_side.flip();
+        facing.0 = away_from_wall;
+        velocity.0 = Vec2::new(
+            away_from_wall.get_sign() * wall_jump_speed.0,
+            jump_speed.0,
+        );
+    }
+}
+
 #[derive(Component)]
 pub struct DashSpeed(pub f32);
 
@@ -280,6 +325,43 @@ fn apply_traction(mut query: Query<(&mut Velocity, &Traction, &FighterState), Wi
     }
 }
 
+#[derive(Component)]
+pub struct AirAcceleration(pub f32);
+
+#[derive(Component)]
+pub struct AirSpeed(pub f32);
+
+fn apply_air_control(
+    mut query: Query<
+        (
+            &FighterState,
+            &mut Velocity,
+            &AirAcceleration,
+            &AirSpeed,
+            &Control,
+        ),
+        With<Airborne>,
+    >,
+) {
+    for (state, mut velocity, acceleration, speed, control) in query.iter_mut() {
+        if !state.has_air_control() {
+            continue;
+        }
+        let target_vx = control.stick.x * speed.0;
+        // Holding forward shouldn't slow down a fighter already drifting faster than their max air speed
+        if target_vx != 0.0 && velocity.0.x * target_vx.signum() > speed.0 {
+            continue;
+        }
+        if (velocity.0.x - target_vx).abs() <= acceleration.0 {
+            velocity.0.x = target_vx;
+        } else if velocity.0.x < target_vx {
+            velocity.0.x += acceleration.0;
+        } else {
+            velocity.0.x -= acceleration.0;
+        }
+    }
+}
+
 fn apply_turnaround(
     mut query: Query<(&mut Facing, &FighterState, &FrameCount), Without<Airborne>>,
 ) {
@@ -296,26 +378,127 @@ fn apply_turnaround(
 }
 
 fn land(
-    q: Query<&FighterState>,
+    mut commands: Commands,
+    q: Query<(&FighterState, &Control, Has<TechWindow>)>,
     mut ev_collision: EventReader<Collision>,
     mut ev_state: EventWriter<FighterStateUpdate>,
+    mut ev_set_velocity: EventWriter<SetVelocity>,
 ) {
     for collision in ev_collision.read() {
-        if collision.normal.x != 0.0 || collision.normal.y <= 0.0 {
+        // Any floor can be landed on, including slopes
+        if collision.wall_side.is_some() || collision.normal.y <= 0.0 {
             continue;
         }
         let entity_id = collision.entity;
-        if let Ok(state) = q.get(entity_id) {
+        if let Ok((state, control, can_tech)) = q.get(entity_id) {
             match state {
-                FighterState::Airdodge(..) | FighterState::IdleAirborne => {
+                FighterState::Airdodge(..)
+                | FighterState::IdleAirborne
+                | FighterState::WallJump(..)
+                | FighterState::LedgeJump => {
                     ev_state.send(FighterStateUpdate(entity_id, FighterState::LandCrouch));
                 }
+                // Tumbling fighters can't land on their feet without teching
+                FighterState::Tumble if can_tech => {
+                    commands
+                        .entity(entity_id)
+                        .remove::<TechWindow>();
+                    ev_state.send(FighterStateUpdate(entity_id, get_floor_tech(control)));
+                }
+                // Fast enough to bounce off the floor, as with hitstun
+                FighterState::Tumble
+                    if collision.incoming_velocity.length() >= STAGE_SPIKE_SPEED_THRESHOLD =>
+                {
+                    ev_state.send(FighterStateUpdate(entity_id, stage_spike(collision)));
+                    ev_set_velocity.send(SetVelocity(entity_id, Vec2::ZERO));
+                }
+                FighterState::Tumble => {
+                    ev_state.send(FighterStateUpdate(entity_id, FighterState::Knockdown));
+                }
                 _ => {}
             }
         }
     }
 }
 
+#[derive(Component)]
+pub struct TouchingWall(pub LeftRight);
+
+fn update_wall_contact(
+    mut commands: Commands,
+    q: Query<Entity, (With<FighterState>, With<TouchingWall>)>,
+    mut ev_collision: EventReader<Collision>,
+) {
+    let mut touching = HashSet::new();
+    for collision in ev_collision.read() {
+        let Some(side) = collision.wall_side else {
+            continue;
+        };
+        if let Some(mut e) = commands.get_entity(collision.entity) {
+            e.insert(TouchingWall(side));
+            touching.insert(collision.entity);
+        }
+    }
+    for entity in q.iter() {
+        if !touching.contains(&entity) {
+            commands
+                .entity(entity)
+                .remove::<TouchingWall>();
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct StandingOnPlatform;
+
+fn update_platform_contact(
+    mut commands: Commands,
+    q_platform: Query<(), With<Platform>>,
+    q_airborne: Query<Entity, (With<StandingOnPlatform>, With<Airborne>)>,
+    mut ev_collision: EventReader<Collision>,
+) {
+    for collision in ev_collision.read() {
+        if collision.wall_side.is_some() || collision.normal.y <= 0.0 {
+            continue;
+        }
+        let Some(mut e) = commands.get_entity(collision.entity) else {
+            continue;
+        };
+        if q_platform.get(collision.collider).is_ok() {
+            e.insert(StandingOnPlatform);
+        } else {
+            e.remove::<StandingOnPlatform>();
+        }
+    }
+    for entity in q_airborne.iter() {
+        commands
+            .entity(entity)
+            .remove::<StandingOnPlatform>();
+    }
+}
+
+fn update_drop_through(
+    mut commands: Commands,
+    q: Query<(Entity, &FighterState, &Control, Has<DropThrough>)>,
+) {
+    for (entity, state, control, is_dropping) in q.iter() {
+        // Airborne fighters fall through platforms while holding down, unless they were hit
+        let should_drop = state == &FighterState::PlatformDrop
+            || (!state.is_grounded()
+                && !state.is_in_hitstun_or_tumble()
+                && control.stick.y < -PLATFORM_DROP_THRESHOLD);
+        if should_drop && !is_dropping {
+            commands
+                .entity(entity)
+                .insert(DropThrough);
+        } else if !should_drop && is_dropping {
+            commands
+                .entity(entity)
+                .remove::<DropThrough>();
+        }
+    }
+}
+
 fn go_airborne(
     q: Query<(Entity, &FighterState), With<Airborne>>,
     mut ev_state: EventWriter<FighterStateUpdate>,
@@ -357,6 +540,228 @@ fn add_intangible(
     }
 }
 
+#[derive(Component)]
+pub struct Invincible;
+
+fn remove_invincible(
+    mut commands: Commands,
+    query: Query<(Entity, &FighterState, &FrameCount), With<Invincible>>,
+) {
+    for (entity, state, frame) in query.iter() {
+        if !state.is_invincible(&frame.0) {
+            commands
+                .entity(entity)
+                .remove::<Invincible>();
+        }
+    }
+}
+
+fn add_invincible(
+    mut commands: Commands,
+    query: Query<(Entity, &FighterState, &FrameCount), Without<Invincible>>,
+) {
+    for (entity, state, frame) in query.iter() {
+        if state.is_invincible(&frame.0) {
+            commands
+                .entity(entity)
+                .insert(Invincible);
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct InHitstun;
+
+fn update_hitstun_status(
+    mut commands: Commands,
+    query: Query<(Entity, &FighterState, Has<InHitstun>)>,
+) {
+    for (entity, state, in_hitstun) in query.iter() {
+        if state.is_in_hitstun() && !in_hitstun {
+            commands
+                .entity(entity)
+                .insert(InHitstun);
+        } else if !state.is_in_hitstun() && in_hitstun {
+            commands
+                .entity(entity)
+                .remove::<InHitstun>();
+        }
+    }
+}
+
+// Frames left in which hitting a surface results in a tech
+#[derive(Component)]
+pub struct TechWindow(pub FrameNumber);
+
+#[derive(Component)]
+pub struct TechLockout(pub FrameNumber);
+
+fn open_tech_window(
+    mut commands: Commands,
+    mut q: Query<(Entity, &FighterState, &mut Control, Has<TechLockout>)>,
+) {
+    for (entity, state, mut control, locked_out) in q.iter_mut() {
+        if !state.can_tech() || locked_out || !control.has_action(&Action::Shield) {
+            continue;
+        }
+        control.action = BufferedInput::None;
+        commands.entity(entity).insert((
+            TechWindow(TECH_WINDOW_FRAMES),
+            TechLockout(TECH_LOCKOUT_FRAMES),
+        ));
+    }
+}
+
+fn close_tech_window(
+    mut commands: Commands,
+    mut q_window: Query<(Entity, &mut TechWindow)>,
+    mut q_lockout: Query<(Entity, &mut TechLockout)>,
+) {
+    for (entity, mut window) in q_window.iter_mut() {
+        window.0 = window.0.saturating_sub(1);
+        if window.0 == 0 {
+            commands
+                .entity(entity)
+                .remove::<TechWindow>();
+        }
+    }
+    for (entity, mut lockout) in q_lockout.iter_mut() {
+        lockout.0 = lockout.0.saturating_sub(1);
+        if lockout.0 == 0 {
+            commands
+                .entity(entity)
+                .remove::<TechLockout>();
+        }
+    }
+}
+
+// Rolls in the held direction, otherwise techs in place
+fn get_floor_tech(control: &Control) -> FighterState {
+    match control
+        .stick
+        .get_cardinal_direction()
+        .and_then(|d| d.horizontal())
+    {
+        Some(direction) => FighterState::TechRoll(direction),
+        None => FighterState::Tech,
+    }
+}
+
+// Fighters in hitstun tech, bounce off of or get knocked down by the surfaces they hit
+fn collide_in_hitstun(
+    mut commands: Commands,
+    q: Query<(&Control, Has<TechWindow>)>,
+    mut ev_collision: EventReader<Collision>,
+    mut ev_state: EventWriter<FighterStateUpdate>,
+    mut ev_set_velocity: EventWriter<SetVelocity>,
+) {
+    let mut handled = HashSet::new();
+    for collision in ev_collision.read() {
+        let entity = collision.entity;
+        if !collision.in_hitstun || handled.contains(&entity) {
+            continue;
+        }
+        let Ok((control, can_tech)) = q.get(entity) else {
+            continue;
+        };
+        let is_floor = collision.wall_side.is_none() && collision.normal.y > 0.0;
+        let incoming = collision.incoming_velocity;
+        let new_state = if can_tech {
+            commands
+                .entity(entity)
+                .remove::<TechWindow>();
+            if is_floor {
+                get_floor_tech(control)
+            } else {
+                FighterState::WallTech
+            }
+        } else if incoming.length() >= STAGE_SPIKE_SPEED_THRESHOLD {
+            stage_spike(collision)
+        } else if is_floor {
+            FighterState::Knockdown
+        } else {
+            continue;
+        };
+        handled.insert(entity);
+        ev_state.send(FighterStateUpdate(entity, new_state));
+        ev_set_velocity.send(SetVelocity(entity, Vec2::ZERO));
+    }
+}
+
+// Bounce off of the surface, preserving speed
+fn stage_spike(collision: &Collision) -> FighterState {
+    let (incoming, normal) = (collision.incoming_velocity, collision.normal);
+    FighterState::StageSpike(incoming - 2.0 * incoming.dot(normal) * normal)
+}
+
+// Stick to the surface, then bounce away on the last frame of the freeze
+fn freeze_stage_spike(mut query: Query<(&FighterState, &FrameCount, &mut Velocity)>) {
+    for (state, frame, mut velocity) in query.iter_mut() {
+        let FighterState::StageSpike(bounce) = state else {
+            continue;
+        };
+        if frame.0 + 1 >= STAGE_SPIKE_FREEZE_FRAMES {
+            velocity.0 = *bounce;
+        } else {
+            velocity.0 = Vec2::ZERO;
+        }
+    }
+}
+
+fn apply_roll_speed(mut query: Query<(&FighterState, &mut Facing, &mut Velocity)>) {
+    for (state, mut facing, mut velocity) in query.iter_mut() {
+        let (FighterState::TechRoll(direction) | FighterState::GetupRoll(direction)) = state else {
+            continue;
+        };
+        facing.0 = *direction;
+        velocity.0.x = direction.get_sign() * ROLL_SPEED;
+    }
+}
+
+// Present from the start of an airdodge until the fighter lands, grabs a ledge or gets hit
+#[derive(Component)]
+pub struct AirdodgeUsed;
+
+// Present from the start of a wall jump until the fighter lands, grabs a ledge or gets hit
+#[derive(Component)]
+pub struct WallJumpUsed;
+
+fn consume_air_options(mut commands: Commands, query: Query<(Entity, &FighterState)>) {
+    for (entity, state) in query.iter() {
+        match state {
+            FighterState::Airdodge(..) => {
+                commands
+                    .entity(entity)
+                    .insert(AirdodgeUsed);
+            }
+            FighterState::WallJump(..) => {
+                commands
+                    .entity(entity)
+                    .insert(WallJumpUsed);
+            }
+            _ => {}
+        }
+    }
+}
+
+// Landing, grabbing a ledge or getting hit gives back the used air options
+fn restore_air_options(
+    mut commands: Commands,
+    query: Query<(Entity, &FighterState, Has<Airborne>)>,
+    q_airdodge_used: Query<(), With<AirdodgeUsed>>,
+    q_wall_jump_used: Query<(), With<WallJumpUsed>>,
+) {
+    for (entity, state, airborne) in query.iter() {
+        let used = q_airdodge_used.contains(entity) || q_wall_jump_used.contains(entity);
+        if !used || airborne && !state.restores_air_options() {
+            continue;
+        }
+        commands
+            .entity(entity)
+            .remove::<(AirdodgeUsed, WallJumpUsed)>();
+    }
+}
+
 fn update_gravity(mut commands: Commands, q: Query<(Entity, &FighterState, &FighterProperties)>) {
     q.iter().for_each(|(e, s, p)| {
         if s.is_affected_by_gravity() {
@@ -369,6 +774,113 @@ fn update_gravity(mut commands: Commands, q: Query<(Entity, &FighterState, &Figh
     })
 }
 
+#[derive(Component)]
+pub struct FallSpeed(pub f32);
+
+#[derive(Component)]
+pub struct FastFalling;
+
+// Granted by landing a hit while airborne, allows a fast fall even while rising
+#[derive(Component)]
+pub struct HitFall;
+
+fn start_fast_fall(
+    mut commands: Commands,
+    mut q: Query<
+        (
+            Entity,
+            &FighterState,
+            &mut Velocity,
+            &mut Control,
+            &FallSpeed,
+        ),
+        Without<FastFalling>,
+    >,
+    q_hit_fall: Query<(), With<HitFall>>,
+) {
+    for (entity, state, mut velocity, mut control, fall_speed) in q.iter_mut() {
+        // Only airborne fighters can have downward velocity or a hit fall pending
+        let can_hit_fall = q_hit_fall.get(entity).is_ok();
+        if !state.can_fast_fall() || (velocity.0.y >= 0.0 && !can_hit_fall) {
+            continue;
+        }
+        let BufferedInput::Some {
+            value: DirectionalAction::Smash(CardinalDirection::Down),
+            ..
+        } = control.directional_action
+        else {
+            continue;
+        };
+        control.directional_action = BufferedInput::None;
+        velocity.0.y = -fall_speed.0 * FAST_FALL_SPEED_MULTIPLIER;
+        commands
+            .entity(entity)
+            .insert(FastFalling)
+            .remove::<HitFall>();
+    }
+}
+
+fn end_fast_fall(
+    mut commands: Commands,
+    q: Query<(Entity, &Velocity, Has<Airborne>), With<FastFalling>>,
+) {
+    for (entity, velocity, is_airborne) in q.iter() {
+        if !is_airborne || velocity.0.y >= 0.0 {
+            commands
+                .entity(entity)
+                .remove::<FastFalling>();
+        }
+    }
+}
+
+fn enable_hit_fall(
+    mut commands: Commands,
+    q: Query<(), (With<FighterState>, With<Airborne>)>,
+    mut ev_hitbox: EventReader<HitboxCollision>,
+) {
+    for hitbox_collision in ev_hitbox.read() {
+        let (HitboxPurpose::Damage { .. }, HitboxPurpose::Body) = (
+            hitbox_collision.target_hitbox.purpose,
+            hitbox_collision.other_hitbox.purpose,
+        ) else {
+            continue;
+        };
+        if q.get(hitbox_collision.target).is_ok() {
+            commands
+                .entity(hitbox_collision.target)
+                .insert(HitFall);
+        }
+    }
+}
+
+// The hit fall is only available until the move that landed the hit ends
+fn disable_hit_fall(
+    mut commands: Commands,
+    q: Query<(Entity, &FighterState, Has<Airborne>), With<HitFall>>,
+) {
+    for (entity, state, is_airborne) in q.iter() {
+        if !is_airborne || !matches!(state, FighterState::Attack(..)) {
+            commands.entity(entity).remove::<HitFall>();
+        }
+    }
+}
+
+fn update_max_fall_speed(
+    mut commands: Commands,
+    q: Query<(Entity, &FallSpeed, Has<FastFalling>)>,
+) {
+    q.iter().for_each(|(e, fall_speed, is_fast_falling)| {
+        let max_fall_speed = if is_fast_falling {
+            fall_speed.0 * FAST_FALL_SPEED_MULTIPLIER
+        } else {
+            fall_speed.0
+        };
+        commands
+            .entity(e)
+            .insert(MaxFallSpeed(max_fall_speed));
+    })
+}
+
 #[derive(Component, Default)]
 pub struct Percent(f32);
 
@@ -381,10 +893,22 @@ impl Default for Weight {
     }
 }
 
+// The other hitbox can belong to anything: another fighter, a projectile or a stage hazard
 fn take_damage_from_hitbox_collision(
-    mut q_fighter: Query<(Entity, &mut Percent, &Weight), With<FighterState>>,
+    mut commands: Commands,
+    mut q_fighter: Query<(
+        Entity,
+        &FighterState,
+        &StateArmour,
+        &FrameCount,
+        &mut Percent,
+        &Weight,
+    )>,
+    q_invincible: Query<(), With<Invincible>>,
+    q_attacker: Query<(&GlobalTransform, Option<&Facing>, Option<&Velocity>)>,
     mut ev_hitbox: EventReader<HitboxCollision>,
     mut ev_set_velocity: EventWriter<SetVelocity>,
+    mut ev_state: EventWriter<FighterStateUpdate>,
 ) {
     for hitbox_collision in ev_hitbox.read() {
         debug!("{:?}", hitbox_collision);
@@ -393,29 +917,72 @@ fn take_damage_from_hitbox_collision(
             base_knockback,
             scale_knockback,
             angle,
+            knockback,
+            direction,
+            reverse_hit,
         } = hitbox_collision.other_hitbox.purpose
         else {
             continue;
         };
-        let Ok((fighter_entity, mut fighter_percent, weight)) =
+        let Ok((fighter_entity, state, armour, frame, mut fighter_percent, weight)) =
             q_fighter.get_mut(hitbox_collision.target)
         else {
             continue;
         };
+        // The hit is still detected, so the attacker can't hit again, but does nothing
+        if q_invincible.contains(fighter_entity) {
+            continue;
+        }
         fighter_percent.0 += percent;
         let launch_speed =
-            weight.0.recip() * (base_knockback + (scale_knockback * fighter_percent.0) * 0.01);
-        let launch_angle = match angle {
-            // Converting CW degrees from 12 o'clock => standard form
-            KnockbackAngle::Fixed(theta) => PI * 0.5 - theta.to_radians(),
+            knockback.launch_speed(base_knockback, scale_knockback, fighter_percent.0, weight.0);
+        if armour.absorbs(&frame.0, launch_speed) {
+            continue;
+        }
+        commands
+            .entity(fighter_entity)
+            .remove::<FastFalling>();
+        if state == &FighterState::Knockdown
+            && frame.0 < JAB_RESET_WINDOW_FRAMES
+            && launch_speed < JAB_RESET_LAUNCH_SPEED_THRESHOLD
+        {
+            ev_set_velocity.send(SetVelocity(fighter_entity, Vec2::ZERO));
+            ev_state.send(FighterStateUpdate(fighter_entity, FighterState::JabReset));
+            continue;
+        }
+        let source = hitbox_collision
+            .other_transform
+            .translation
+            .xy();
+        let target = hitbox_collision
+            .target_transform
+            .translation
+            .xy();
+        // Without an attacker to go by, the hitbox itself is where the hit came from
+        let (facing, velocity, attacker_x) = match q_attacker.get(hitbox_collision.other) {
+            Ok((attacker_transform, facing, velocity)) => (
+                facing.map(|facing| facing.0),
+                velocity.map(|velocity| velocity.0),
+                attacker_transform.translation().x,
+            ),
+            Err(..) => (None, None, source.x),
+        };
+        let side = knockback_side(&direction, reverse_hit, facing, velocity, attacker_x, target.x);
+        let launched = launch(
+            &angle,
+            launch_speed,
+            side,
+            source,
+            target,
+            state.is_grounded(),
+        );
+        let hitstun = hitstun_frames(launch_speed);
+        let (launch_velocity, next_state) = match launched {
+            Launch::Airborne(velocity) => (velocity, FighterState::Hitstun(hitstun)),
+            Launch::Grounded(velocity) => (velocity, FighterState::GroundedHitstun(hitstun)),
         };
-        let launch_velocity = Vec2::from_angle(launch_angle)
-            * launch_speed
-            * hitbox_collision
-                .other_transform
-                .scale
-                .xy();
         ev_set_velocity.send(SetVelocity(fighter_entity, launch_velocity));
+        ev_state.send(FighterStateUpdate(fighter_entity, next_state));
     }
 }
 
@@ -462,13 +1029,26 @@ impl Plugin for FighterPlugin {
                             accelerate_to_run_speed,
                             accelerate_to_moonwalk_speed,
                             accelerate_to_walk_speed,
-                            set_airdodge_speed,
+                            (set_airdodge_speed, freeze_stage_spike, apply_roll_speed).chain(),
+                            apply_wall_jump_speed,
+                            (apply_air_control, end_fast_fall, start_fast_fall).chain(),
                             update_gravity,
-                            land,
+                            update_max_fall_speed,
+                            (
+                                land,
+                                update_wall_contact,
+                                update_platform_contact,
+                                collide_in_hitstun,
+                            )
+                                .chain(),
+                            update_drop_through,
                             go_airborne,
-                            remove_intangible,
-                            add_intangible,
+                            (remove_intangible, add_intangible, remove_invincible, add_invincible)
+                                .chain(),
+                            (restore_air_options, consume_air_options).chain(),
                             take_damage_from_hitbox_collision,
+                            (enable_hit_fall, disable_hit_fall).chain(),
+                            (close_tech_window, open_tech_window, update_hitstun_status).chain(),
                         )
                             .chain()
                             .in_set(FighterEventSet::React),
@@ -494,6 +1074,7 @@ pub struct FighterBundle {
     pub velocity: Velocity,
     pub state: FighterState,
     pub state_transition_properties: FighterStateTransition,
+    pub armour: StateArmour,
     pub properties: FighterProperties,
     pub animation_indices: AnimationIndices,
     pub animation_timer: AnimationTimer,
@@ -505,4 +1086,369 @@ pub struct FighterBundle {
     pub dash_speed: DashSpeed,
     pub run_speed: RunSpeed,
     pub walk_speed: WalkSpeed,
+    pub air_acceleration: AirAcceleration,
+    pub air_speed: AirSpeed,
+    pub fall_speed: FallSpeed,
+    pub wall_jump_speed: WallJumpSpeed,
+    pub pushbox: Pushbox,
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::hitbox::{Hitbox, KnockbackAngle, Shape};
+
+    // Whether both used air options are given back to a fighter in this state
+    fn restores_air_options(state: FighterState, airborne: bool) -> bool {
+        let mut world = World::new();
+        let entity = world
+            .spawn((state, AirdodgeUsed, WallJumpUsed))
+            .id();
+        if airborne {
+            world
+                .entity_mut(entity)
+                .insert(Airborne);
+        }
+        world.run_system_once(restore_air_options);
+        let fighter = world.entity(entity);
+        !fighter.contains::<AirdodgeUsed>() && !fighter.contains::<WallJumpUsed>()
+    }
+
+    #[test]
+    fn landing_restores_air_options() {
+        assert!(restores_air_options(FighterState::LandCrouch, false));
+    }
+
+    #[test]
+    fn ledge_grab_restores_air_options() {
+        assert!(restores_air_options(FighterState::LedgeHang, true));
+    }
+
+    #[test]
+    fn getting_hit_restores_air_options() {
+        assert!(restores_air_options(FighterState::Hitstun(20), true));
+    }
+
+    #[test]
+    fn air_options_stay_used_while_airborne() {
+        assert!(!restores_air_options(FighterState::IdleAirborne, true));
+    }
+
+    // Whether a fighter in this state holding down falls through platforms
+    fn drops_through_holding_down(state: FighterState) -> bool {
+        let mut world = World::new();
+        let mut control = Control::default();
+        control.stick = Vec2::NEG_Y;
+        let entity = world.spawn((state, control)).id();
+        world.run_system_once(update_drop_through);
+        world
+            .entity(entity)
+            .contains::<DropThrough>()
+    }
+
+    #[test]
+    fn holding_down_drops_through_platforms() {
+        assert!(drops_through_holding_down(FighterState::IdleAirborne));
+    }
+
+    #[test]
+    fn no_drop_through_in_hitstun_or_tumble() {
+        assert!(!drops_through_holding_down(FighterState::Hitstun(20)));
+        assert!(!drops_through_holding_down(FighterState::Tumble));
+    }
+
+    // Hits a fighter in the given state, returning the world after the hit and the fighter
+    fn hit_fighter(
+        state: FighterState,
+        frame: FrameNumber,
+        base_knockback: f32,
+        invincible: bool,
+    ) -> (World, Entity) {
+        let mut world = World::new();
+        world.init_resource::<Events<HitboxCollision>>();
+        world.init_resource::<Events<SetVelocity>>();
+        world.init_resource::<Events<FighterStateUpdate>>();
+        let target = world
+            .spawn((
+                state,
+                StateArmour::default(),
+                FrameCount(frame),
+                Percent::default(),
+                Weight::default(),
+            ))
+            .id();
+        if invincible {
+            world
+                .entity_mut(target)
+                .insert(Invincible);
+        }
+        let attacker = world
+            .spawn(GlobalTransform::default())
+            .id();
+        let circle = Shape::Circle(10.0);
+        world.send_event(HitboxCollision {
+            target,
+            other: attacker,
+            target_group: target,
+            other_group: attacker,
+            target_hitbox: Hitbox::default(),
+            other_hitbox: Hitbox {
+                purpose: HitboxPurpose::Damage {
+                    percent: 10.0,
+                    base_knockback,
+                    scale_knockback: 1.0,
+                    angle: KnockbackAngle::Fixed(45.0),
+                    knockback: default(),
+                    direction: default(),
+                    reverse_hit: false,
+                },
+                ..default()
+            },
+            target_transform: Transform::default(),
+            other_transform: Transform::default(),
+            nearest_pass: Shape::nearest_pass(
+                &circle,
+                &Transform::default(),
+                &circle,
+                &Transform::default(),
+            ),
+        });
+        world.run_system_once(take_damage_from_hitbox_collision);
+        (world, target)
+    }
+
+    fn percent_after_hit(invincible: bool) -> f32 {
+        let (world, target) = hit_fighter(FighterState::Idle, 0, 5.0, invincible);
+        world
+            .get::<Percent>(target)
+            .unwrap()
+            .0
+    }
+
+    fn state_after_hit(
+        state: FighterState,
+        frame: FrameNumber,
+        base_knockback: f32,
+    ) -> Option<FighterState> {
+        let (mut world, _) = hit_fighter(state, frame, base_knockback, false);
+        world
+            .resource_mut::<Events<FighterStateUpdate>>()
+            .drain()
+            .last()
+            .map(|update| update.1)
+    }
+
+    #[test]
+    fn hits_deal_damage() {
+        assert_eq!(percent_after_hit(false), 10.0);
+    }
+
+    #[test]
+    fn invincible_fighters_take_no_damage() {
+        assert_eq!(percent_after_hit(true), 0.0);
+    }
+
+    #[test]
+    fn weak_hit_during_knockdown_jab_resets() {
+        assert_eq!(
+            state_after_hit(FighterState::Knockdown, 0, 2.0),
+            Some(FighterState::JabReset)
+        );
+    }
+
+    #[test]
+    fn strong_hit_during_knockdown_launches() {
+        assert!(matches!(
+            state_after_hit(FighterState::Knockdown, 0, 20.0),
+            Some(FighterState::Hitstun(..))
+        ));
+    }
+
+    #[test]
+    fn no_jab_reset_after_the_window() {
+        assert_ne!(
+            state_after_hit(FighterState::Knockdown, JAB_RESET_WINDOW_FRAMES, 2.0),
+            Some(FighterState::JabReset)
+        );
+    }
+
+    const FALL_SPEED: f32 = 5.0;
+
+    // Spawns an airborne fighter that has just flicked the stick down
+    fn spawn_flicking_down(world: &mut World, state: FighterState, vertical_speed: f32) -> Entity {
+        let mut control = Control::default();
+        control.directional_action = BufferedInput::Some {
+            value: DirectionalAction::Smash(CardinalDirection::Down),
+            stick: Vec2::NEG_Y,
+            age: 0,
+        };
+        world
+            .spawn((
+                state,
+                control,
+                Velocity(Vec2::new(0.0, vertical_speed)),
+                FallSpeed(FALL_SPEED),
+                Airborne,
+            ))
+            .id()
+    }
+
+    #[test]
+    fn fast_fall_only_starts_after_the_apex() {
+        let mut world = World::new();
+        let rising = spawn_flicking_down(&mut world, FighterState::IdleAirborne, 1.0);
+        let falling = spawn_flicking_down(&mut world, FighterState::IdleAirborne, -1.0);
+        world.run_system_once(start_fast_fall);
+        assert!(!world.entity(rising).contains::<FastFalling>());
+        assert!(world.entity(falling).contains::<FastFalling>());
+        assert_eq!(
+            world.get::<Velocity>(falling).unwrap().0.y,
+            -FALL_SPEED * FAST_FALL_SPEED_MULTIPLIER
+        );
+    }
+
+    #[test]
+    fn fast_fall_raises_max_fall_speed() {
+        let mut world = World::new();
+        let normal = world.spawn(FallSpeed(FALL_SPEED)).id();
+        let fast = world
+            .spawn((FallSpeed(FALL_SPEED), FastFalling))
+            .id();
+        world.run_system_once(update_max_fall_speed);
+        assert_eq!(world.get::<MaxFallSpeed>(normal).unwrap().0, FALL_SPEED);
+        assert_eq!(
+            world.get::<MaxFallSpeed>(fast).unwrap().0,
+            FALL_SPEED * FAST_FALL_SPEED_MULTIPLIER
+        );
+    }
+
+    #[test]
+    fn fast_fall_ends_on_landing() {
+        let mut world = World::new();
+        let airborne = world
+            .spawn((Velocity(Vec2::NEG_Y), FastFalling, Airborne))
+            .id();
+        let landed = world
+            .spawn((Velocity(Vec2::NEG_Y), FastFalling))
+            .id();
+        world.run_system_once(end_fast_fall);
+        assert!(world.entity(airborne).contains::<FastFalling>());
+        assert!(!world.entity(landed).contains::<FastFalling>());
+    }
+
+    #[test]
+    fn hit_fall_starts_fast_fall_while_rising() {
+        let mut world = World::new();
+        let entity = spawn_flicking_down(&mut world, FighterState::Attack(0), 1.0);
+        world
+            .entity_mut(entity)
+            .insert(HitFall);
+        world.run_system_once(disable_hit_fall);
+        world.run_system_once(start_fast_fall);
+        assert!(world.entity(entity).contains::<FastFalling>());
+        assert!(!world.entity(entity).contains::<HitFall>());
+    }
+
+    #[test]
+    fn hit_fall_ends_with_the_move() {
+        let mut world = World::new();
+        let entity = spawn_flicking_down(&mut world, FighterState::IdleAirborne, 1.0);
+        world
+            .entity_mut(entity)
+            .insert(HitFall);
+        world.run_system_once(disable_hit_fall);
+        world.run_system_once(start_fast_fall);
+        assert!(!world.entity(entity).contains::<FastFalling>());
+    }
+
+    // State a fighter is sent to after hitting a surface, by either landing or hitting it in
+    // hitstun, and whether their tech window was used up
+    fn state_after_collision(
+        state: FighterState,
+        can_tech: bool,
+        normal: Vec2,
+        incoming_velocity: Vec2,
+    ) -> (Option<FighterState>, bool) {
+        let mut world = World::new();
+        world.init_resource::<Events<Collision>>();
+        world.init_resource::<Events<FighterStateUpdate>>();
+        world.init_resource::<Events<SetVelocity>>();
+        let entity = world
+            .spawn((state, Control::default()))
+            .id();
+        if can_tech {
+            world
+                .entity_mut(entity)
+                .insert(TechWindow(1));
+        }
+        let collider = world.spawn_empty().id();
+        world.send_event(Collision {
+            entity,
+            collider,
+            normal,
+            // Walls in these tests are all to the left of the fighter
+            wall_side: (normal == Vec2::X).then_some(LeftRight::Left),
+            incoming_velocity,
+            in_hitstun: state.is_in_hitstun(),
+        });
+        world.run_system_once(land);
+        world.run_system_once(collide_in_hitstun);
+        let new_state = world
+            .resource_mut::<Events<FighterStateUpdate>>()
+            .drain()
+            .last()
+            .map(|update| update.1);
+        (new_state, world.entity(entity).contains::<TechWindow>())
+    }
+
+    #[test]
+    fn tech_inside_the_window() {
+        let floor_tech = get_floor_tech(&Control::default());
+        assert_eq!(
+            state_after_collision(FighterState::Hitstun(20), true, Vec2::Y, Vec2::NEG_Y),
+            (Some(floor_tech), false)
+        );
+        assert_eq!(
+            state_after_collision(FighterState::Tumble, true, Vec2::Y, Vec2::NEG_Y),
+            (Some(floor_tech), false)
+        );
+        assert_eq!(
+            state_after_collision(FighterState::Hitstun(20), true, Vec2::X, Vec2::NEG_X),
+            (Some(FighterState::WallTech), false)
+        );
+    }
+
+    #[test]
+    fn missed_tech_leads_to_knockdown() {
+        assert_eq!(
+            state_after_collision(FighterState::Hitstun(20), false, Vec2::Y, Vec2::NEG_Y),
+            (Some(FighterState::Knockdown), false)
+        );
+        assert_eq!(
+            state_after_collision(FighterState::Tumble, false, Vec2::Y, Vec2::NEG_Y),
+            (Some(FighterState::Knockdown), false)
+        );
+    }
+
+    #[test]
+    fn hitting_a_wall_in_hitstun_without_teching_stage_spikes() {
+        let incoming = Vec2::new(-STAGE_SPIKE_SPEED_THRESHOLD, 1.0);
+        assert_eq!(
+            state_after_collision(FighterState::Hitstun(20), false, Vec2::X, incoming),
+            (
+                Some(FighterState::StageSpike(Vec2::new(STAGE_SPIKE_SPEED_THRESHOLD, 1.0))),
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn slowly_touching_a_wall_in_hitstun_does_nothing() {
+        assert_eq!(
+            state_after_collision(FighterState::Hitstun(20), false, Vec2::X, Vec2::NEG_X),
+            (None, false)
+        );
+    }
 }
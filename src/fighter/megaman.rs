@@ -3,11 +3,13 @@ use bevy::prelude::*;
 
 use crate::{
     fighter::{FighterEventSet, FighterStateUpdate},
-    fighter_state::{StateEnd, IASA},
+    fighter_state::{Armour, FrameRange, StateArmour, StateEnd, IASA},
     hitbox::{
-        Hitbox, HitboxBundle, HitboxGroup, HitboxGroupBundle, HitboxPurpose, KnockbackAngle, Shape,
+        Hitbox, HitboxBundle, HitboxGroup, HitboxGroupBundle, HitboxPurpose, KnockbackAngle,
+        KnockbackDirection, KnockbackMode, Shape,
     },
     input::Action,
+    physics::Pushbox,
     projectile::Projectile,
     utils::{Facing, FrameCount, FrameNumber, LeftRight, Lifetime},
     AnimationIndices, AnimationUpdate, AnimationUpdateEvent, Velocity,
@@ -16,11 +18,22 @@ use crate::{
 const ATTACK_DURATION: FrameNumber = 20;
 const ATTACK_SHOOT_FRAME: FrameNumber = 5;
 const ATTACK_IASA: FrameNumber = 10;
+const LEDGE_ATTACK_SHOOT_FRAME: FrameNumber = 25;
+const GETUP_ATTACK_SHOOT_FRAME: FrameNumber = 18;
+// The last attack of the combo isn't interrupted by weak hits before it shoots
+const FINAL_ATTACK_ARMOUR_LAUNCH_SPEED: f32 = 6.0;
+// The last attack of the combo shoots a lemon which hits again every few frames while it passes
+// through a target
+const FINAL_LEMON_HIT_INTERVAL: FrameNumber = 3;
 
 pub const MEGAMAN_TRACTION: f32 = 0.5;
 pub const MEGAMAN_JUMP_SPEED: f32 = 10.0;
 pub const MEGAMAN_DASH_SPEED: f32 = 5.0;
 pub const MEGAMAN_WALK_SPEED: f32 = 3.0;
+pub const MEGAMAN_AIR_ACCELERATION: f32 = 0.2;
+pub const MEGAMAN_AIR_SPEED: f32 = 3.0;
+pub const MEGAMAN_FALL_SPEED: f32 = 6.0;
+pub const MEGAMAN_WALL_JUMP_SPEED: f32 = 6.0;
 
 #[derive(Component)]
 pub struct MegaMan;
@@ -38,6 +51,16 @@ impl MegaMan {
         }
     }
 
+    pub fn get_pushbox() -> Pushbox {
+        Pushbox {
+            shape: Shape::Pill {
+                major_radius: 20.0,
+                minor_radius: 14.0,
+            },
+            offset: Vec2::new(0.0, 40.0),
+        }
+    }
+
     pub fn spawn_body_hitboxes(child_builder: &mut ChildBuilder) {
         child_builder
             .spawn(HitboxGroupBundle {
@@ -52,6 +75,7 @@ impl MegaMan {
                             minor_radius: 15.5,
                         },
                         purpose: HitboxPurpose::Body,
+                        ..Default::default()
                     },
                     transform: TransformBundle {
                         local: Transform::from_xyz(-1.0, 20.75, 1.0),
@@ -101,17 +125,26 @@ fn get_attack_transition<const STAGE: u8>() -> FighterStateTransition {
 
 fn update_state_transition_rules(
     mut q: Query<
-        (&mut FighterStateTransition, &FighterState),
+        (&mut FighterStateTransition, &mut StateArmour, &FighterState),
         (With<MegaMan>, Changed<FighterState>),
     >,
 ) {
-    for (mut transition, state) in q.iter_mut() {
+    for (mut transition, mut armour, state) in q.iter_mut() {
         *transition = match state {
             FighterState::Attack(0) => get_attack_transition::<0>(),
             FighterState::Attack(1) => get_attack_transition::<1>(),
             FighterState::Attack(..) => get_attack_transition::<MAX_ATTACK_STAGE>(),
             _ => FighterStateTransition::default_for_state(state),
         };
+        *armour = match state {
+            FighterState::Attack(stage) if *stage >= MAX_ATTACK_STAGE => {
+                StateArmour(vec![Armour {
+                    frames: FrameRange(0, ATTACK_SHOOT_FRAME),
+                    launch_speed_threshold: FINAL_ATTACK_ARMOUR_LAUNCH_SPEED,
+                }])
+            }
+            _ => StateArmour::default(),
+        };
         debug!("{:?}", transition);
     }
 }
@@ -173,6 +206,27 @@ fn animation_for_state(state: &FighterState) -> Option<AnimationUpdate> {
             seconds_per_frame: 0.1,
         }),
         FighterState::Airdodge(..) => Some(AnimationUpdate::SingleFrame(33)),
+        FighterState::WallJump(..) | FighterState::LedgeJump => {
+            Some(AnimationUpdate::SingleFrame(18))
+        }
+        FighterState::LedgeHang | FighterState::PlatformDrop => {
+            Some(AnimationUpdate::SingleFrame(19))
+        }
+        FighterState::LedgeGetup
+        | FighterState::LedgeRoll
+        | FighterState::Tech
+        | FighterState::TechRoll(..) => Some(AnimationUpdate::SingleFrame(133)),
+        FighterState::Hitstun(..)
+        | FighterState::Tumble
+        | FighterState::StageSpike(..)
+        | FighterState::WallTech => Some(AnimationUpdate::SingleFrame(19)),
+        FighterState::Knockdown => Some(AnimationUpdate::SingleFrame(134)),
+        FighterState::GroundedHitstun(..) => Some(AnimationUpdate::SingleFrame(133)),
+        FighterState::JabReset
+        | FighterState::GetupStand
+        | FighterState::GetupRoll(..) => Some(AnimationUpdate::SingleFrame(133)),
+        FighterState::GetupAttack => Some(AnimationUpdate::SingleFrame(43)),
+        FighterState::LedgeAttack => Some(AnimationUpdate::SingleFrame(43)),
         FighterState::Dash => Some(AnimationUpdate::SingleFrame(24)),
         FighterState::Turnaround => Some(AnimationUpdate::SingleFrame(74)),
         FighterState::RunTurnaround => Some(AnimationUpdate::SingleFrame(30)),
@@ -227,6 +281,20 @@ impl LemonBundle {
 
 const MAX_ATTACK_STAGE: u8 = 2;
 
+// Frames until the lemon's hitbox group refreshes
+#[derive(Component)]
+struct MultiHitLemon(FrameNumber);
+
+fn refresh_multi_hit_lemons(mut q: Query<(&mut MultiHitLemon, &mut HitboxGroup)>) {
+    for (mut lemon, mut group) in q.iter_mut() {
+        lemon.0 = lemon.0.saturating_sub(1);
+        if lemon.0 == 0 {
+            group.refresh();
+            lemon.0 = FINAL_LEMON_HIT_INTERVAL;
+        }
+    }
+}
+
 fn shoot_lemon(
     mut commands: Commands,
     mut q: Query<(
@@ -239,51 +307,49 @@ fn shoot_lemon(
     lemon_sprite: Res<LemonSprite>,
 ) {
     for (entity, state, FrameCount(frame), global_transform, facing) in q.iter_mut() {
-        let FighterState::Attack(..) = state else {
-            continue;
+        let shoot_frame = match state {
+            FighterState::Attack(..) => ATTACK_SHOOT_FRAME,
+            FighterState::LedgeAttack => LEDGE_ATTACK_SHOOT_FRAME,
+            FighterState::GetupAttack => GETUP_ATTACK_SHOOT_FRAME,
+            _ => continue,
         };
 
-        if frame != &ATTACK_SHOOT_FRAME {
+        if frame != &shoot_frame {
             continue;
         }
 
         let lemon_position = Vec3::new(20.0, 23.0, 10.0);
         let mut transform = global_transform.compute_transform();
         transform.translation += lemon_position * transform.scale;
-        // transform.scale.x *= facing.0.get_sign();
 
-        commands
-            .spawn(LemonBundle::new(
-                entity,
-                lemon_sprite.0.clone().unwrap(),
-                facing,
-                transform,
-            ))
-            .with_children(|parent| {
-                parent.spawn(HitboxBundle {
-                    // transform: TransformBundle {
-                    //     local: Transform::from_scale(Vec3::new(
-                    //         match facing.0 {
-                    //             LeftRight::Left => -1.0,
-                    //             LeftRight::Right => 1.0,
-                    //         },
-                    //         1.0,
-                    //         1.0,
-                    //     )),
-                    //     ..Default::default()
-                    // },
-                    hitbox: Hitbox {
-                        shape: Shape::Circle(5.0),
-                        purpose: HitboxPurpose::Damage {
-                            percent: 3.0,
-                            base_knockback: 0.1,
-                            scale_knockback: 5.0,
-                            angle: KnockbackAngle::Fixed(45.0),
-                        },
+        let mut lemon = commands.spawn(LemonBundle::new(
+            entity,
+            lemon_sprite.0.clone().unwrap(),
+            facing,
+            transform,
+        ));
+        if matches!(state, FighterState::Attack(stage) if *stage >= MAX_ATTACK_STAGE) {
+            lemon.insert(MultiHitLemon(FINAL_LEMON_HIT_INTERVAL));
+        }
+        lemon.with_children(|parent| {
+            parent.spawn(HitboxBundle {
+                hitbox: Hitbox {
+                    shape: Shape::Circle(5.0),
+                    purpose: HitboxPurpose::Damage {
+                        percent: 3.0,
+                        base_knockback: 0.1,
+                        scale_knockback: 5.0,
+                        angle: KnockbackAngle::Fixed(45.0),
+                        knockback: KnockbackMode::Scaling,
+                        direction: KnockbackDirection::Velocity,
+                        reverse_hit: false,
                     },
+                    swept: true,
                     ..Default::default()
-                });
+                },
+                ..Default::default()
             });
+        });
     }
 }
 
@@ -298,6 +364,7 @@ impl Plugin for MegaManPlugin {
                     (
                         // update_state_for_frame_count,
                         shoot_lemon,
+                        refresh_multi_hit_lemons,
                         emit_animation_update,
                     )
                         .chain()
@@ -2,10 +2,12 @@ use bevy::{ecs::world::DeferredWorld, prelude::*};
 
 use crate::{
     input::{Action, BufferedInput, Control, DirectionalAction, RotationDirection},
-    utils::{CardinalDirection, Directed, Facing, FrameCount, FrameNumber},
+    utils::{CardinalDirection, Directed, Facing, FrameCount, FrameNumber, LeftRight},
 };
 
-use crate::fighter::CROUCH_THRESHOLD;
+use crate::fighter::{
+    AirdodgeUsed, StandingOnPlatform, TouchingWall, WallJumpUsed, CROUCH_THRESHOLD,
+};
 
 #[derive(Component, Clone, Copy, Default, Debug, PartialEq)]
 pub enum FighterState {
@@ -26,6 +28,31 @@ pub enum FighterState {
     // Ensures that the player cannot Dash out of a Run by going Run -> Idle -> Dash
     RunEnd,
     Airdodge(Vec2),
+    // Side of the fighter that the wall was on
+    WallJump(LeftRight),
+    LedgeHang,
+    LedgeGetup,
+    LedgeRoll,
+    LedgeAttack,
+    LedgeJump,
+    PlatformDrop,
+    // Number of frames of hitstun
+    Hitstun(FrameNumber),
+    // Hitstun from a weak non-upward hit that doesn't launch a grounded fighter
+    GroundedHitstun(FrameNumber),
+    // Airborne after a strong hit until the fighter acts or lands
+    Tumble,
+    // Velocity to bounce away with after the freeze
+    StageSpike(Vec2),
+    Tech,
+    TechRoll(LeftRight),
+    WallTech,
+    Knockdown,
+    // Forced to get up after being hit while knocked down
+    JabReset,
+    GetupStand,
+    GetupAttack,
+    GetupRoll(LeftRight),
     Attack(u8),
 }
 
@@ -35,6 +62,23 @@ impl FighterState {
             Self::Airdodge(..) => {
                 &AIRDODGE_INTANGIBLE_START <= frame && frame <= &AIRDODGE_INTANGIBLE_END
             }
+            Self::LedgeHang => (0..=LEDGE_HANG_INTANGIBLE_END).contains(frame),
+            Self::LedgeGetup => true,
+            Self::LedgeRoll => (0..=LEDGE_ROLL_INTANGIBLE_END).contains(frame),
+            Self::LedgeAttack => (0..=LEDGE_ATTACK_INTANGIBLE_END).contains(frame),
+            Self::Tech => (0..=TECH_INTANGIBLE_END).contains(frame),
+            Self::TechRoll(..) => (0..=TECH_ROLL_INTANGIBLE_END).contains(frame),
+            Self::WallTech => (0..=WALL_TECH_INTANGIBLE_END).contains(frame),
+            Self::GetupStand => (0..=GETUP_STAND_INTANGIBLE_END).contains(frame),
+            Self::GetupAttack => (0..=GETUP_ATTACK_INTANGIBLE_END).contains(frame),
+            Self::GetupRoll(..) => (0..=GETUP_ROLL_INTANGIBLE_END).contains(frame),
+            _ => false,
+        }
+    }
+    // Invincible fighters are still hit, but take no damage or knockback
+    pub fn is_invincible(&self, frame: &FrameNumber) -> bool {
+        match self {
+            Self::GetupAttack => (0..=GETUP_ATTACK_INVINCIBLE_END).contains(frame),
             _ => false,
         }
     }
@@ -52,24 +96,106 @@ impl FighterState {
             | Self::Moonwalk
             | Self::Crouch
             | Self::EnterCrouch
-            | Self::ExitCrouch => true,
+            | Self::ExitCrouch
+            | Self::Tech
+            | Self::TechRoll(..)
+            | Self::GroundedHitstun(..)
+            | Self::Knockdown
+            | Self::JabReset
+            | Self::GetupStand
+            | Self::GetupAttack
+            | Self::GetupRoll(..) => true,
             _ => false,
         }
     }
     pub fn is_exempt_from_normal_traction(&self) -> bool {
         match self {
-            Self::JumpSquat | Self::Walk | Self::Run | Self::Dash | Self::Moonwalk => true,
+            Self::JumpSquat
+            | Self::Walk
+            | Self::Run
+            | Self::Dash
+            | Self::Moonwalk
+            | Self::TechRoll(..)
+            | Self::GetupRoll(..) => true,
             _ => false,
         }
     }
+    // Only applies while airborne, since attacks can also be grounded
+    pub fn has_air_control(&self) -> bool {
+        matches!(
+            self,
+            Self::IdleAirborne | Self::Tumble | Self::PlatformDrop | Self::Attack(..)
+        )
+    }
+    pub fn can_fast_fall(&self) -> bool {
+        matches!(self, Self::IdleAirborne | Self::Tumble | Self::Attack(..))
+    }
+    pub fn can_grab_ledge(&self) -> bool {
+        matches!(self, Self::IdleAirborne)
+    }
+    // States in which the fighter's position is pinned to a ledge
+    pub fn is_holding_ledge(&self) -> bool {
+        matches!(
+            self,
+            Self::LedgeHang | Self::LedgeGetup | Self::LedgeRoll | Self::LedgeAttack
+        )
+    }
+    pub fn restores_air_options(&self) -> bool {
+        matches!(self, Self::LedgeHang | Self::Hitstun(..))
+    }
+    pub fn is_in_hitstun(&self) -> bool {
+        matches!(self, Self::Hitstun(..))
+    }
+    pub fn is_in_hitstun_or_tumble(&self) -> bool {
+        matches!(self, Self::Hitstun(..) | Self::Tumble)
+    }
+    pub fn can_tech(&self) -> bool {
+        self.is_in_hitstun_or_tumble()
+    }
     pub fn is_affected_by_gravity(&self) -> bool {
         match self {
-            Self::Airdodge(..) => false,
+            Self::Airdodge(..) | Self::LedgeHang | Self::StageSpike(..) => false,
             _ => true,
         }
     }
 }
 
+// Inclusive range of frames within a state
+#[derive(Debug, Clone, Copy)]
+pub struct FrameRange(pub FrameNumber, pub FrameNumber);
+
+impl FrameRange {
+    pub fn contains(&self, frame: &FrameNumber) -> bool {
+        (self.0..=self.1).contains(frame)
+    }
+}
+
+// Armoured fighters take damage without being interrupted or launched, unless the hit's
+// launch speed reaches the threshold (see GDD)
+#[derive(Debug, Clone, Copy)]
+pub struct Armour {
+    pub frames: FrameRange,
+    pub launch_speed_threshold: f32,
+}
+
+impl Armour {
+    pub fn absorbs(&self, frame: &FrameNumber, launch_speed: f32) -> bool {
+        self.frames.contains(frame) && launch_speed < self.launch_speed_threshold
+    }
+}
+
+// Armour periods of the current state, set per character like FighterStateTransition
+#[derive(Component, Default, Debug)]
+pub struct StateArmour(pub Vec<Armour>);
+
+impl StateArmour {
+    pub fn absorbs(&self, frame: &FrameNumber, launch_speed: f32) -> bool {
+        self.0
+            .iter()
+            .any(|armour| armour.absorbs(frame, launch_speed))
+    }
+}
+
 #[derive(Component, Default, Debug)]
 pub struct FighterStateTransition {
     pub end: StateEnd,
@@ -84,6 +210,45 @@ pub const AIRDODGE_INTANGIBLE_END: FrameNumber = 15;
 pub const TURNAROUND_DURATION_FRAMES: FrameNumber = 8;
 pub const RUN_TURNAROUND_DURATION_FRAMES: FrameNumber = 8;
 pub const CROUCH_TRANSITION_THRESHOLD_FRAME: FrameNumber = 6;
+pub const WALL_JUMP_DURATION_FRAMES: FrameNumber = 10;
+pub const LEDGE_HANG_IASA: FrameNumber = 8;
+pub const LEDGE_HANG_MAX_DURATION_FRAMES: FrameNumber = 300;
+pub const LEDGE_HANG_INTANGIBLE_END: FrameNumber = 30;
+pub const LEDGE_GETUP_DURATION_FRAMES: FrameNumber = 30;
+pub const LEDGE_ROLL_DURATION_FRAMES: FrameNumber = 40;
+pub const LEDGE_ROLL_INTANGIBLE_END: FrameNumber = 30;
+pub const LEDGE_ATTACK_DURATION_FRAMES: FrameNumber = 40;
+pub const LEDGE_ATTACK_INTANGIBLE_END: FrameNumber = 20;
+pub const LEDGE_JUMP_DURATION_FRAMES: FrameNumber = 10;
+pub const PLATFORM_DROP_DURATION_FRAMES: FrameNumber = 4;
+pub const STAGE_SPIKE_FREEZE_FRAMES: FrameNumber = 6;
+pub const TECH_DURATION_FRAMES: FrameNumber = 26;
+pub const TECH_INTANGIBLE_END: FrameNumber = 20;
+pub const TECH_ROLL_DURATION_FRAMES: FrameNumber = 40;
+pub const TECH_ROLL_INTANGIBLE_END: FrameNumber = 20;
+pub const WALL_TECH_DURATION_FRAMES: FrameNumber = 12;
+pub const WALL_TECH_INTANGIBLE_END: FrameNumber = 10;
+pub const KNOCKDOWN_IASA: FrameNumber = 10;
+pub const KNOCKDOWN_MAX_DURATION_FRAMES: FrameNumber = 90;
+// Knocked down fighters can only be jab reset this many frames into the knockdown
+pub const JAB_RESET_WINDOW_FRAMES: FrameNumber = 30;
+pub const JAB_RESET_DURATION_FRAMES: FrameNumber = 20;
+pub const GETUP_STAND_DURATION_FRAMES: FrameNumber = 30;
+pub const GETUP_STAND_INTANGIBLE_END: FrameNumber = 22;
+pub const GETUP_ATTACK_DURATION_FRAMES: FrameNumber = 40;
+pub const GETUP_ATTACK_INTANGIBLE_END: FrameNumber = 20;
+// Trades through hits until the attack comes out
+pub const GETUP_ATTACK_INVINCIBLE_END: FrameNumber = 30;
+pub const GETUP_ROLL_DURATION_FRAMES: FrameNumber = 35;
+pub const GETUP_ROLL_INTANGIBLE_END: FrameNumber = 20;
+// Fighters with at least this much hitstun tumble once it ends
+pub const TUMBLE_HITSTUN_THRESHOLD: FrameNumber = 32;
+// Hitstun is proportional to launch speed (see GDD)
+pub const HITSTUN_FRAMES_PER_LAUNCH_SPEED: f32 = 4.0;
+
+pub fn hitstun_frames(launch_speed: f32) -> FrameNumber {
+    (launch_speed * HITSTUN_FRAMES_PER_LAUNCH_SPEED).round() as FrameNumber
+}
 
 pub const DEFAULT_LAND_CROUCH_DURATION: FrameNumber = 6;
 pub const DEFAULT_JUMP_SQUAT_DURATION: FrameNumber = 6;
@@ -203,6 +368,25 @@ fn try_crouch(data: &InterruptPlayerData) -> Option<FighterState> {
     }
 }
 
+fn try_platform_drop(data: &InterruptPlayerData) -> Option<FighterState> {
+    data.component::<StandingOnPlatform>()?;
+    let flicked_down = matches!(
+        data.control.directional_action,
+        BufferedInput::Some {
+            value: DirectionalAction::Smash(CardinalDirection::Down),
+            ..
+        }
+    );
+    // Shield drop
+    let shielded_down = data.control.has_action(&Action::Shield)
+        && data.control.stick.y < -CROUCH_THRESHOLD;
+    if flicked_down || shielded_down {
+        Some(FighterState::PlatformDrop)
+    } else {
+        None
+    }
+}
+
 fn try_end_crouch(data: &InterruptPlayerData) -> Option<FighterState> {
     if data.control.stick.y >= -CROUCH_THRESHOLD {
         Some(FighterState::ExitCrouch)
@@ -242,7 +426,8 @@ fn try_end_walk(data: &InterruptPlayerData) -> Option<FighterState> {
 }
 
 fn try_airdodge(data: &InterruptPlayerData) -> Option<FighterState> {
-    if data.control.has_action(&Action::Shield) {
+    // Only one airdodge is allowed before landing
+    if data.control.has_action(&Action::Shield) && data.component::<AirdodgeUsed>().is_none() {
         Some(FighterState::Airdodge(
             data.control.stick.normalize_or_zero(),
         ))
@@ -251,6 +436,61 @@ fn try_airdodge(data: &InterruptPlayerData) -> Option<FighterState> {
     }
 }
 
+fn try_wall_jump(data: &InterruptPlayerData) -> Option<FighterState> {
+    let TouchingWall(side) = data.component::<TouchingWall>()?;
+    if data.component::<WallJumpUsed>().is_some() {
+        return None;
+    }
+    if data.control.has_action(&Action::Jump)
+        && let Some(direction) = data
+            .control
+            .stick
+            .get_cardinal_direction()
+        && direction == *side
+    {
+        Some(FighterState::WallJump(*side))
+    } else {
+        None
+    }
+}
+
+fn try_ledge_option(data: &InterruptPlayerData) -> Option<FighterState> {
+    if data.control.has_action(&Action::Jump) {
+        return Some(FighterState::LedgeJump);
+    }
+    if data.control.has_action(&Action::Attack) {
+        return Some(FighterState::LedgeAttack);
+    }
+    if data.control.has_action(&Action::Shield) {
+        return Some(FighterState::LedgeRoll);
+    }
+    // Fighters always face the stage while holding a ledge
+    let facing = data
+        .component::<Facing>()
+        .expect("Player facing");
+    match data.control.stick.get_cardinal_direction() {
+        Some(CardinalDirection::Up) => Some(FighterState::LedgeGetup),
+        Some(CardinalDirection::Down) => Some(FighterState::IdleAirborne),
+        Some(direction) if direction == facing.0 => Some(FighterState::LedgeGetup),
+        Some(..) => Some(FighterState::IdleAirborne),
+        None => None,
+    }
+}
+
+fn try_getup_option(data: &InterruptPlayerData) -> Option<FighterState> {
+    if data.control.has_action(&Action::Attack) {
+        return Some(FighterState::GetupAttack);
+    }
+    let direction = data.control.stick.get_cardinal_direction();
+    if let Some(horizontal) = direction.and_then(|d| d.horizontal()) {
+        return Some(FighterState::GetupRoll(horizontal));
+    }
+    if data.control.has_action(&Action::Jump) || direction == Some(CardinalDirection::Up) {
+        return Some(FighterState::GetupStand);
+    }
+    None
+}
+
 fn try_attack(data: &InterruptPlayerData) -> Option<FighterState> {
     if data.control.has_action(&Action::Attack) {
         Some(FighterState::Attack(0))
@@ -267,6 +507,7 @@ impl FighterStateTransition {
                 .or_else(|| try_jump(data))
                 .or_else(|| try_turnaround(data))
                 .or_else(|| try_walk(data))
+                .or_else(|| try_platform_drop(data))
                 .or_else(|| try_crouch(data))
         }
     }
@@ -328,12 +569,16 @@ impl FighterStateTransition {
                     frame: CROUCH_TRANSITION_THRESHOLD_FRAME,
                     next_state: FighterState::Crouch,
                 },
-                ..Default::default()
+                iasa: IASA::immediate(try_platform_drop),
             },
 
             FighterState::Crouch => Self {
                 end: StateEnd::None,
-                iasa: IASA::immediate(|data| try_jump(data).or_else(|| try_end_crouch(data))),
+                iasa: IASA::immediate(|data| {
+                    try_jump(data)
+                        .or_else(|| try_platform_drop(data))
+                        .or_else(|| try_end_crouch(data))
+                }),
             },
 
             FighterState::ExitCrouch => Self {
@@ -349,7 +594,15 @@ impl FighterStateTransition {
             },
 
             FighterState::IdleAirborne => Self {
-                iasa: IASA::immediate(try_airdodge),
+                iasa: IASA::immediate(|data| try_wall_jump(data).or_else(|| try_airdodge(data))),
+                ..Default::default()
+            },
+
+            FighterState::WallJump(..) => Self {
+                end: StateEnd::OnFrame {
+                    frame: WALL_JUMP_DURATION_FRAMES,
+                    next_state: FighterState::IdleAirborne,
+                },
                 ..Default::default()
             },
 
@@ -361,6 +614,110 @@ impl FighterStateTransition {
                 ..Default::default()
             },
 
+            FighterState::LedgeHang => Self {
+                end: StateEnd::OnFrame {
+                    frame: LEDGE_HANG_MAX_DURATION_FRAMES,
+                    next_state: FighterState::IdleAirborne,
+                },
+                iasa: IASA::new(LEDGE_HANG_IASA, try_ledge_option),
+            },
+
+            FighterState::LedgeGetup => Self {
+                end: StateEnd::idle_on_frame(LEDGE_GETUP_DURATION_FRAMES),
+                ..Default::default()
+            },
+
+            FighterState::LedgeRoll => Self {
+                end: StateEnd::idle_on_frame(LEDGE_ROLL_DURATION_FRAMES),
+                ..Default::default()
+            },
+
+            FighterState::LedgeAttack => Self {
+                end: StateEnd::idle_on_frame(LEDGE_ATTACK_DURATION_FRAMES),
+                ..Default::default()
+            },
+
+            FighterState::LedgeJump => Self {
+                end: StateEnd::OnFrame {
+                    frame: LEDGE_JUMP_DURATION_FRAMES,
+                    next_state: FighterState::IdleAirborne,
+                },
+                iasa: IASA::immediate(try_airdodge),
+            },
+
+            FighterState::PlatformDrop => Self {
+                end: StateEnd::OnFrame {
+                    frame: PLATFORM_DROP_DURATION_FRAMES,
+                    next_state: FighterState::IdleAirborne,
+                },
+                ..Default::default()
+            },
+
+            FighterState::Hitstun(duration) => Self {
+                end: StateEnd::OnFrame {
+                    frame: *duration,
+                    next_state: if *duration >= TUMBLE_HITSTUN_THRESHOLD {
+                        FighterState::Tumble
+                    } else {
+                        FighterState::IdleAirborne
+                    },
+                },
+                ..Default::default()
+            },
+
+            FighterState::GroundedHitstun(duration) => Self {
+                end: StateEnd::OnFrame {
+                    frame: *duration,
+                    next_state: FighterState::Idle,
+                },
+                ..Default::default()
+            },
+
+            FighterState::Tumble => Self {
+                iasa: IASA::immediate(|data| {
+                    try_wall_jump(data)
+                        .or_else(|| try_airdodge(data))
+                        .or_else(|| try_attack(data))
+                }),
+                ..Default::default()
+            },
+
+            FighterState::StageSpike(bounce) => Self {
+                end: StateEnd::OnFrame {
+                    frame: STAGE_SPIKE_FREEZE_FRAMES,
+                    next_state: FighterState::Hitstun(hitstun_frames(bounce.length())),
+                },
+                ..Default::default()
+            },
+
+            FighterState::Tech => Self::idle_on_frame(TECH_DURATION_FRAMES),
+
+            FighterState::TechRoll(..) => Self::idle_on_frame(TECH_ROLL_DURATION_FRAMES),
+
+            FighterState::WallTech => Self {
+                end: StateEnd::OnFrame {
+                    frame: WALL_TECH_DURATION_FRAMES,
+                    next_state: FighterState::IdleAirborne,
+                },
+                ..Default::default()
+            },
+
+            FighterState::Knockdown => Self {
+                end: StateEnd::OnFrame {
+                    frame: KNOCKDOWN_MAX_DURATION_FRAMES,
+                    next_state: FighterState::GetupStand,
+                },
+                iasa: IASA::new(KNOCKDOWN_IASA, try_getup_option),
+            },
+
+            FighterState::JabReset => Self::idle_on_frame(JAB_RESET_DURATION_FRAMES),
+
+            FighterState::GetupStand => Self::idle_on_frame(GETUP_STAND_DURATION_FRAMES),
+
+            FighterState::GetupAttack => Self::idle_on_frame(GETUP_ATTACK_DURATION_FRAMES),
+
+            FighterState::GetupRoll(..) => Self::idle_on_frame(GETUP_ROLL_DURATION_FRAMES),
+
             FighterState::Dash => Self {
                 end: StateEnd::OnFrame {
                     frame: DEFAULT_DASH_DURATION,
@@ -489,7 +846,7 @@ pub fn apply_state_transition(
                 FighterState::Dash | FighterState::Moonwalk => {
                     control.directional_action = BufferedInput::None;
                 }
-                FighterState::Airdodge(..) => {
+                FighterState::Airdodge(..) | FighterState::PlatformDrop => {
                     control.directional_action = BufferedInput::None;
                     control.action = BufferedInput::None;
                 }
@@ -511,3 +868,180 @@ pub fn apply_state_transition(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn try_airdodge_with_shield(world: &mut World, entity: Entity) -> Option<FighterState> {
+        let mut control = Control::default();
+        control.action = BufferedInput::Some {
+            value: Action::Shield,
+            stick: Vec2::ZERO,
+            age: 0,
+        };
+        let world = DeferredWorld::from(world);
+        try_airdodge(&InterruptPlayerData {
+            control: &control,
+            state: &FighterState::IdleAirborne,
+            entity: &entity,
+            world: &world,
+        })
+    }
+
+    fn try_wall_jump_towards(
+        world: &mut World,
+        entity: Entity,
+        stick: Vec2,
+    ) -> Option<FighterState> {
+        let mut control = Control::default();
+        control.stick = stick;
+        control.action = BufferedInput::Some {
+            value: Action::Jump,
+            stick,
+            age: 0,
+        };
+        let world = DeferredWorld::from(world);
+        try_wall_jump(&InterruptPlayerData {
+            control: &control,
+            state: &FighterState::IdleAirborne,
+            entity: &entity,
+            world: &world,
+        })
+    }
+
+    fn try_getup_option_with(action: Option<Action>, stick: Vec2) -> Option<FighterState> {
+        let mut control = Control::default();
+        control.stick = stick;
+        if let Some(action) = action {
+            control.action = BufferedInput::Some {
+                value: action,
+                stick,
+                age: 0,
+            };
+        }
+        let mut world = World::new();
+        let entity = world.spawn_empty().id();
+        let world = DeferredWorld::from(&mut world);
+        try_getup_option(&InterruptPlayerData {
+            control: &control,
+            state: &FighterState::Knockdown,
+            entity: &entity,
+            world: &world,
+        })
+    }
+
+    #[test]
+    fn armour_absorbs_weak_hits_during_its_frames() {
+        let armour = Armour {
+            frames: FrameRange(2, 5),
+            launch_speed_threshold: 6.0,
+        };
+        assert!(armour.absorbs(&2, 5.9));
+        assert!(armour.absorbs(&5, 0.0));
+        assert!(!armour.absorbs(&3, 6.0));
+        assert!(!armour.absorbs(&1, 1.0));
+        assert!(!armour.absorbs(&6, 1.0));
+    }
+
+    #[test]
+    fn any_armour_in_a_state_absorbs() {
+        let armour = StateArmour(vec![
+            Armour {
+                frames: FrameRange(0, 3),
+                launch_speed_threshold: f32::INFINITY,
+            },
+            Armour {
+                frames: FrameRange(10, 20),
+                launch_speed_threshold: 4.0,
+            },
+        ]);
+        assert!(armour.absorbs(&3, 100.0));
+        assert!(armour.absorbs(&12, 3.0));
+        assert!(!armour.absorbs(&12, 5.0));
+        assert!(!StateArmour::default().absorbs(&0, 0.0));
+    }
+
+    #[test]
+    fn first_airdodge_is_allowed() {
+        let mut world = World::new();
+        let entity = world.spawn_empty().id();
+        assert_eq!(
+            try_airdodge_with_shield(&mut world, entity),
+            Some(FighterState::Airdodge(Vec2::ZERO))
+        );
+    }
+
+    #[test]
+    fn used_airdodge_blocks_another() {
+        let mut world = World::new();
+        let entity = world.spawn(AirdodgeUsed).id();
+        assert_eq!(try_airdodge_with_shield(&mut world, entity), None);
+    }
+
+    #[test]
+    fn wall_jump_towards_a_touched_wall() {
+        let mut world = World::new();
+        let entity = world
+            .spawn(TouchingWall(LeftRight::Left))
+            .id();
+        assert_eq!(
+            try_wall_jump_towards(&mut world, entity, Vec2::NEG_X),
+            Some(FighterState::WallJump(LeftRight::Left))
+        );
+        assert_eq!(try_wall_jump_towards(&mut world, entity, Vec2::X), None);
+    }
+
+    #[test]
+    fn no_wall_jump_without_wall_contact() {
+        let mut world = World::new();
+        let entity = world.spawn_empty().id();
+        assert_eq!(try_wall_jump_towards(&mut world, entity, Vec2::NEG_X), None);
+    }
+
+    #[test]
+    fn used_wall_jump_blocks_another() {
+        let mut world = World::new();
+        let entity = world
+            .spawn((TouchingWall(LeftRight::Left), WallJumpUsed))
+            .id();
+        assert_eq!(try_wall_jump_towards(&mut world, entity, Vec2::NEG_X), None);
+    }
+
+    #[test]
+    fn getup_attack() {
+        assert_eq!(
+            try_getup_option_with(Some(Action::Attack), Vec2::X),
+            Some(FighterState::GetupAttack)
+        );
+    }
+
+    #[test]
+    fn getup_roll_towards_the_stick() {
+        assert_eq!(
+            try_getup_option_with(None, Vec2::NEG_X),
+            Some(FighterState::GetupRoll(LeftRight::Left))
+        );
+        assert_eq!(
+            try_getup_option_with(Some(Action::Jump), Vec2::X),
+            Some(FighterState::GetupRoll(LeftRight::Right))
+        );
+    }
+
+    #[test]
+    fn getup_stand() {
+        assert_eq!(
+            try_getup_option_with(None, Vec2::Y),
+            Some(FighterState::GetupStand)
+        );
+        assert_eq!(
+            try_getup_option_with(Some(Action::Jump), Vec2::ZERO),
+            Some(FighterState::GetupStand)
+        );
+    }
+
+    #[test]
+    fn knockdown_lasts_without_input() {
+        assert_eq!(try_getup_option_with(None, Vec2::ZERO), None);
+    }
+}
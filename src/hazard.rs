@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    fighter::FighterEventSet,
+    hitbox::{Hitbox, HitboxBundle, HitboxGroup},
+    utils::{FrameCount, FrameNumber},
+};
+
+// Hitboxes are active from frame `start` until frame `end` of every `period` frames, or only
+// once if `period` is 0. Each activation can hit a fighter once, unless there is a rehit rate
+#[derive(Component, Deserialize, Clone, Copy)]
+pub struct HazardSchedule {
+    pub period: FrameNumber,
+    pub start: FrameNumber,
+    pub end: FrameNumber,
+}
+
+impl HazardSchedule {
+    fn is_activating(&self, frame: FrameNumber) -> bool {
+        if self.period == 0 {
+            return frame == self.start;
+        }
+        frame % self.period == self.start
+    }
+
+    fn is_deactivating(&self, frame: FrameNumber) -> bool {
+        self.period != 0 && self.end < self.period && frame % self.period == self.end
+    }
+}
+
+#[derive(Deserialize)]
+pub struct HazardHitbox {
+    pub hitbox: Hitbox,
+    #[serde(default)]
+    pub offset: Vec2,
+    // Degrees, CW
+    #[serde(default)]
+    pub rotation: f32,
+}
+
+#[derive(Deserialize)]
+pub struct HazardDescription {
+    pub position: Vec2,
+    pub hitboxes: Vec<HazardHitbox>,
+    // Always active if there is no schedule
+    #[serde(default)]
+    pub schedule: Option<HazardSchedule>,
+    // Degrees per frame, CW
+    #[serde(default)]
+    pub spin: f32,
+    // Frames before the same fighter can be hit again while active
+    #[serde(default)]
+    pub rehit_rate: Option<FrameNumber>,
+}
+
+// Lets the hazard's group be recreated with the same rehit rate
+#[derive(Component)]
+struct HazardRehitRate(Option<FrameNumber>);
+
+// Part of the stage that damages fighters
+#[derive(Component)]
+pub struct Hazard;
+
+#[derive(Component)]
+pub struct Spin(pub f32);
+
+// Hazards can be turned off for competitive play
+#[derive(Resource)]
+pub struct HazardsEnabled(pub bool);
+
+impl Default for HazardsEnabled {
+    fn default() -> Self {
+        HazardsEnabled(!std::env::args().any(|arg| arg == "--no-hazards"))
+    }
+}
+
+pub fn spawn_hazard(commands: &mut Commands, hazard: &HazardDescription) -> Entity {
+    let mut entity = commands.spawn((
+        Hazard,
+        FrameCount(0),
+        HazardRehitRate(hazard.rehit_rate),
+        SpatialBundle::from_transform(Transform::from_translation(hazard.position.extend(1.0))),
+    ));
+    entity.with_children(|parent| {
+        for hazard_hitbox in hazard.hitboxes.iter() {
+            parent.spawn(HitboxBundle {
+                hitbox: hazard_hitbox.hitbox.clone(),
+                transform: TransformBundle::from_transform(
+                    Transform::from_translation(hazard_hitbox.offset.extend(0.0))
+                        .with_rotation(Quat::from_rotation_z(-hazard_hitbox.rotation.to_radians())),
+                ),
+            });
+        }
+    });
+    match hazard.schedule {
+        Some(schedule) => entity.insert((schedule, Visibility::Hidden)),
+        None => entity.insert(HitboxGroup::default().with_rehit_rate(hazard.rehit_rate)),
+    };
+    if hazard.spin != 0.0 {
+        entity.insert(Spin(hazard.spin));
+    }
+    entity.id()
+}
+
+fn update_hazard_schedules(
+    mut commands: Commands,
+    mut q: Query<
+        (Entity, &HazardSchedule, &FrameCount, &HazardRehitRate, &mut Visibility),
+        With<Hazard>,
+    >,
+    mut q_group: Query<&mut HitboxGroup>,
+) {
+    for (entity, schedule, frame, rehit_rate, mut visibility) in q.iter_mut() {
+        if schedule.is_activating(frame.0) {
+            // Each activation can hit everyone again
+            match q_group.get_mut(entity) {
+                Ok(mut group) => group.refresh(),
+                Err(..) => {
+                    commands
+                        .entity(entity)
+                        .insert(HitboxGroup::default().with_rehit_rate(rehit_rate.0));
+                }
+            }
+            *visibility = Visibility::Inherited;
+        } else if schedule.is_deactivating(frame.0) {
+            commands.entity(entity).remove::<HitboxGroup>();
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
+fn spin_hazards(mut q: Query<(&Spin, &FrameCount, &mut Transform), With<Hazard>>) {
+    for (spin, frame, mut transform) in q.iter_mut() {
+        transform.rotation = Quat::from_rotation_z(-(spin.0 * frame.0 as f32).to_radians());
+    }
+}
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HazardsEnabled>()
+            .add_systems(
+                FixedUpdate,
+                (update_hazard_schedules, spin_hazards).in_set(FighterEventSet::Act),
+            );
+    }
+}
@@ -1,11 +1,19 @@
-use crate::fighter::FighterEventSet;
-use crate::utils::VisibleDuringDebug;
+use crate::fighter::{FighterEventSet, Intangible};
+use crate::utils::{FrameNumber, LeftRight, VisibleDuringDebug};
 use bevy::{
     prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
 };
 use itertools::Itertools;
-use std::collections::HashSet;
+use serde::{de::Error as _, Deserialize, Deserializer};
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::PI,
+};
 
 #[derive(Debug, Clone, Copy)]
 pub struct NearestPass {
@@ -18,6 +26,11 @@ impl NearestPass {
     fn is_collision(&self) -> bool {
         self.distance <= 0.0
     }
+
+    // Negative when the shapes overlap
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
 }
 
 impl PartialEq for NearestPass {
@@ -43,13 +56,32 @@ impl Ord for NearestPass {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug, Deserialize)]
 pub enum Shape {
     Circle(f32),
     Pill {
         major_radius: f32,
         minor_radius: f32,
     },
+    // Oriented by the hitbox's rotation
+    Rect {
+        half_width: f32,
+        half_height: f32,
+    },
+    // Vertices of a convex polygon, in order. Loaded as the convex hull of the given vertices
+    Polygon(#[serde(deserialize_with = "deserialize_convex_polygon")] Vec<Vec2>),
+}
+
+fn deserialize_convex_polygon<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Vec2>, D::Error> {
+    let hull = convex_hull(Vec::<Vec2>::deserialize(deserializer)?);
+    if hull.len() < 3 {
+        return Err(D::Error::custom(
+            "polygon needs at least 3 vertices which aren't all in a line",
+        ));
+    }
+    Ok(hull)
 }
 
 impl Default for Shape {
@@ -58,6 +90,11 @@ impl Default for Shape {
     }
 }
 
+// Circles and pills are scaled by the transform's vertical scale, ignoring flips
+fn scaled_radius(radius: f32, transform: &Transform) -> f32 {
+    radius * transform.scale.y.abs()
+}
+
 /*
 "Radius" and two endpoints
 */
@@ -70,21 +107,21 @@ fn get_pill_geometric_data(
     let rotated_a = transform.rotation.mul_vec3(a);
     let rotated_b = -rotated_a;
     return (
-        minor_radius * transform.scale.y,
+        scaled_radius(minor_radius, transform),
         (rotated_a + transform.translation).xy(),
         (rotated_b + transform.translation).xy(),
     );
 }
 
 impl Shape {
-    fn nearest_pass(s1: &Self, t1: &Transform, s2: &Self, t2: &Transform) -> NearestPass {
+    pub fn nearest_pass(s1: &Self, t1: &Transform, s2: &Self, t2: &Transform) -> NearestPass {
         match (s1, s2) {
             (Shape::Circle(r1), Shape::Circle(r2)) => {
                 let p1 = t1.translation.xy();
                 let p2 = t2.translation.xy();
                 return NearestPass {
                     midpoint: 0.5 * (p1 + p2),
-                    distance: (p1 - p2).length() - r1 * t1.scale.y - r2 * t2.scale.y,
+                    distance: (p1 - p2).length() - scaled_radius(*r1, t1) - scaled_radius(*r2, t2),
                 };
             }
             (
@@ -95,7 +132,7 @@ impl Shape {
                 },
             ) => {
                 let c = t1.translation.xy();
-                let r1 = r * t1.scale.x.abs();
+                let r1 = scaled_radius(*r, t1);
                 let (r2, a, b) = get_pill_geometric_data(*major_radius, *minor_radius, t2);
                 // Distance to endpoints
                 let d_a = (c - a).length();
@@ -179,8 +216,331 @@ impl Shape {
                 .reduce(std::cmp::min)
                 .expect("Pill-pill distance")
             }
+            (Shape::Rect { .. } | Shape::Polygon(..), _)
+            | (_, Shape::Rect { .. } | Shape::Polygon(..)) => {
+                let (core_1, r1) = s1.convex_core(t1);
+                let (core_2, r2) = s2.convex_core(t2);
+                let (midpoint, distance) = core_distance(&core_1, &core_2);
+                NearestPass {
+                    midpoint,
+                    distance: distance - r1 - r2,
+                }
+            }
+        }
+    }
+
+    /*
+    Every shape is a convex "core" grown by a radius: a point for circles, a line segment for
+    pills and a polygon for rects and polygons
+    */
+    fn convex_core(&self, transform: &Transform) -> (Vec<Vec2>, f32) {
+        match self {
+            Shape::Circle(r) => (vec![transform.translation.xy()], scaled_radius(*r, transform)),
+            Shape::Pill {
+                major_radius,
+                minor_radius,
+            } => {
+                let (r, a, b) = get_pill_geometric_data(*major_radius, *minor_radius, transform);
+                (vec![a, b], r)
+            }
+            Shape::Rect {
+                half_width,
+                half_height,
+            } => (
+                transform_points(&rect_vertices(*half_width, *half_height), transform),
+                0.0,
+            ),
+            Shape::Polygon(vertices) => {
+                // Loading rejects these, so one would have to be built by hand
+                debug_assert!(!vertices.is_empty(), "Polygon without vertices");
+                (transform_points(vertices, transform), 0.0)
+            }
+        }
+    }
+}
+
+fn rect_vertices(half_width: f32, half_height: f32) -> [Vec2; 4] {
+    [
+        Vec2::new(-half_width, -half_height),
+        Vec2::new(half_width, -half_height),
+        Vec2::new(half_width, half_height),
+        Vec2::new(-half_width, half_height),
+    ]
+}
+
+fn transform_points(points: &[Vec2], transform: &Transform) -> Vec<Vec2> {
+    points
+        .iter()
+        .map(|point| {
+            transform
+                .transform_point(point.extend(0.0))
+                .xy()
+        })
+        .collect()
+}
+
+// Edges of a convex core, where a line segment is a single edge
+fn core_edges(core: &[Vec2]) -> Vec<(Vec2, Vec2)> {
+    match core.len() {
+        0 | 1 => vec![],
+        2 => vec![(core[0], core[1])],
+        _ => core
+            .iter()
+            .copied()
+            .circular_tuple_windows()
+            .collect(),
+    }
+}
+
+fn closest_point_on_segment(point: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let ab = b - a;
+    if ab.length_squared() == 0.0 {
+        return a;
+    }
+    let t = ((point - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0);
+    a + ab * t
+}
+
+// How far two convex cores overlap along an axis, negative if they don't
+fn overlap_on_axis(core_1: &[Vec2], core_2: &[Vec2], axis: Vec2) -> f32 {
+    let project = |core: &[Vec2]| {
+        core.iter()
+            .map(|point| point.dot(axis))
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), x| {
+                (min.min(x), max.max(x))
+            })
+    };
+    let (min_1, max_1) = project(core_1);
+    let (min_2, max_2) = project(core_2);
+    (max_1 - min_2).min(max_2 - min_1)
+}
+
+/*
+Signed distance between two convex cores, and a point between them. This is the gap between
+them when they are apart, or minus the shortest distance that would separate them when they
+overlap (separating axis theorem)
+*/
+fn core_distance(core_1: &[Vec2], core_2: &[Vec2]) -> (Vec2, f32) {
+    let edges_1 = core_edges(core_1);
+    let edges_2 = core_edges(core_2);
+    let axes = edges_1
+        .iter()
+        .chain(edges_2.iter())
+        .filter_map(|(a, b)| (*b - *a).try_normalize())
+        .flat_map(|direction| [direction.perp(), direction]);
+    let penetration = axes
+        .map(|axis| overlap_on_axis(core_1, core_2, axis))
+        .reduce(f32::min);
+    if let Some(depth) = penetration
+        && depth > 0.0
+    {
+        let centroid = |core: &[Vec2]| core.iter().sum::<Vec2>() / core.len() as f32;
+        return (0.5 * (centroid(core_1) + centroid(core_2)), -depth);
+    }
+    for ((a1, b1), (a2, b2)) in edges_1.iter().cartesian_product(edges_2.iter()) {
+        if let Some(intersection) = intersection_of_line_segments(a1, b1, a2, b2) {
+            return (intersection, 0.0);
+        }
+    }
+    // Apart, so the nearest points include a vertex of one of the cores
+    let nearest_to = |point: Vec2, edges: &[(Vec2, Vec2)], core: &[Vec2]| match edges.len() {
+        0 => core[0],
+        _ => edges
+            .iter()
+            .map(|(a, b)| closest_point_on_segment(point, *a, *b))
+            .min_by(|p, q| point.distance(*p).total_cmp(&point.distance(*q)))
+            .expect("Nearest point on edges"),
+    };
+    core_1
+        .iter()
+        .map(|point| (*point, nearest_to(*point, &edges_2, core_2)))
+        .chain(
+            core_2
+                .iter()
+                .map(|point| (nearest_to(*point, &edges_1, core_1), *point)),
+        )
+        .map(|(p, q)| (0.5 * (p + q), p.distance(q)))
+        .min_by(|(_, d1), (_, d2)| d1.total_cmp(d2))
+        .expect("Convex distance")
+}
+
+// Andrew's monotone chain
+fn convex_hull(mut points: Vec<Vec2>) -> Vec<Vec2> {
+    points.sort_by(|p, q| p.x.total_cmp(&q.x).then(p.y.total_cmp(&q.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let mut hull: Vec<Vec2> = Vec::with_capacity(2 * points.len());
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let start = hull.len();
+        for point in pass {
+            while hull.len() >= start + 2
+                && cross_product(
+                    &(hull[hull.len() - 1] - hull[hull.len() - 2]),
+                    &(point - hull[hull.len() - 2]),
+                ) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(point);
         }
+        hull.pop();
     }
+    hull
+}
+
+impl Shape {
+    /*
+    Like `nearest_pass`, but each shape with a previous transform covers the area it swept
+    through since then: a circle becomes a pill, and a pill becomes the convex hull of its
+    previous and current positions
+    */
+    pub fn swept_nearest_pass(
+        s1: &Self,
+        t1: &Transform,
+        previous_1: Option<&Transform>,
+        s2: &Self,
+        t2: &Transform,
+        previous_2: Option<&Transform>,
+    ) -> NearestPass {
+        let pieces_1 = s1.swept_pieces(t1, previous_1);
+        let pieces_2 = s2.swept_pieces(t2, previous_2);
+        let nearest = pieces_1
+            .iter()
+            .cartesian_product(pieces_2.iter())
+            .map(|((shape_1, transform_1), (shape_2, transform_2))| {
+                Self::nearest_pass(shape_1, transform_1, shape_2, transform_2)
+            })
+            .reduce(std::cmp::min)
+            .expect("Swept distance");
+        // The pieces only cover the outline of a swept pill, so check its inside too
+        [
+            s1.swept_hull_pass(t1, previous_1, s2, t2),
+            s2.swept_hull_pass(t2, previous_2, s1, t1),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(nearest, std::cmp::min)
+    }
+
+    // Axis-aligned bounding box, including the swept area if there is a previous transform
+    pub fn swept_bounds(&self, transform: &Transform, previous: Option<&Transform>) -> Rect {
+        let bounds = self.bounds(transform);
+        match previous {
+            Some(previous) => bounds.union(self.bounds(previous)),
+            None => bounds,
+        }
+    }
+
+    fn bounds(&self, transform: &Transform) -> Rect {
+        match self {
+            Shape::Circle(r) => Rect::from_center_half_size(
+                transform.translation.xy(),
+                Vec2::splat(scaled_radius(*r, transform)),
+            ),
+            Shape::Pill {
+                major_radius,
+                minor_radius,
+            } => {
+                let (r, a, b) = get_pill_geometric_data(*major_radius, *minor_radius, transform);
+                Rect::from_corners(a, b).inflate(r)
+            }
+            Shape::Rect { .. } | Shape::Polygon(..) => {
+                let (core, _) = self.convex_core(transform);
+                let start = Rect::from_center_size(
+                    core.first()
+                        .copied()
+                        .unwrap_or(transform.translation.xy()),
+                    Vec2::ZERO,
+                );
+                core.into_iter()
+                    .fold(start, |rect, point| rect.union_point(point))
+            }
+        }
+    }
+
+    // Shapes which together cover the outline of the swept area
+    fn swept_pieces(
+        &self,
+        transform: &Transform,
+        previous: Option<&Transform>,
+    ) -> Vec<(Self, Transform)> {
+        let Some(previous) = previous else {
+            return vec![(self.clone(), *transform)];
+        };
+        match self {
+            Shape::Circle(r) => vec![pill_between(
+                previous.translation.xy(),
+                transform.translation.xy(),
+                scaled_radius(*r, transform),
+            )],
+            Shape::Pill {
+                major_radius,
+                minor_radius,
+            } => {
+                let (r, a, b) = get_pill_geometric_data(*major_radius, *minor_radius, transform);
+                let (_, a0, b0) = get_pill_geometric_data(*major_radius, *minor_radius, previous);
+                // Every edge of the convex hull is one of these
+                [(a, b), (a0, b0), (a0, a), (b0, b), (a0, b), (b0, a)]
+                    .into_iter()
+                    .map(|(p, q)| pill_between(p, q, r))
+                    .collect()
+            }
+            Shape::Rect { .. } | Shape::Polygon(..) => {
+                let (mut points, _) = self.convex_core(previous);
+                points.extend(self.convex_core(transform).0);
+                vec![(Shape::Polygon(convex_hull(points)), Transform::IDENTITY)]
+            }
+        }
+    }
+
+    /*
+    Nearest pass between the hull swept by a pill's core, grown by the pill's radius, and
+    another shape. A pill that doesn't move, or only moves along its core, sweeps a line segment
+    */
+    fn swept_hull_pass(
+        &self,
+        transform: &Transform,
+        previous: Option<&Transform>,
+        other: &Self,
+        other_transform: &Transform,
+    ) -> Option<NearestPass> {
+        let (
+            Shape::Pill {
+                major_radius,
+                minor_radius,
+            },
+            Some(previous),
+        ) = (self, previous)
+        else {
+            return None;
+        };
+        let (r, a, b) = get_pill_geometric_data(*major_radius, *minor_radius, transform);
+        let (_, a0, b0) = get_pill_geometric_data(*major_radius, *minor_radius, previous);
+        let (other_core, other_r) = other.convex_core(other_transform);
+        let (midpoint, distance) = core_distance(&convex_hull(vec![a0, b0, a, b]), &other_core);
+        Some(NearestPass {
+            midpoint,
+            distance: distance - r - other_r,
+        })
+    }
+}
+
+// Pill whose core runs from `p` to `q`
+fn pill_between(p: Vec2, q: Vec2, radius: f32) -> (Shape, Transform) {
+    let centre = Transform::from_translation((0.5 * (p + q)).extend(0.0));
+    let d = q - p;
+    if d.length_squared() <= f32::EPSILON {
+        return (Shape::Circle(radius), centre);
+    }
+    (
+        Shape::Pill {
+            major_radius: 0.5 * d.length(),
+            minor_radius: radius,
+        },
+        centre.with_rotation(Quat::from_rotation_z(Vec2::Y.angle_between(d))),
+    )
 }
 
 fn cross_product(v: &Vec2, w: &Vec2) -> f32 {
@@ -228,7 +588,7 @@ fn intersection_of_line_segments(p1: &Vec2, p2: &Vec2, q1: &Vec2, q2: &Vec2) ->
     }
 }
 
-#[derive(Default, Clone, Copy, Debug)]
+#[derive(Default, Clone, Copy, Debug, Deserialize)]
 pub enum HitboxPurpose {
     #[default]
     Body,
@@ -238,19 +598,205 @@ pub enum HitboxPurpose {
         base_knockback: f32,
         scale_knockback: f32,
         angle: KnockbackAngle,
+        #[serde(default)]
+        knockback: KnockbackMode,
+        #[serde(default)]
+        direction: KnockbackDirection,
+        // Targets behind the attacker are launched backwards
+        #[serde(default)]
+        reverse_hit: bool,
     },
 }
 
-// TODO: Other types of knockback
-#[derive(Clone, Copy, Debug)]
+// Which way fixed knockback angles point horizontally
+#[derive(Default, Clone, Copy, Debug, Deserialize)]
+pub enum KnockbackDirection {
+    // The way the attacker is facing
+    #[default]
+    Facing,
+    // The way the attacker is moving, e.g. for projectiles
+    Velocity,
+}
+
+#[derive(Default, Clone, Copy, Debug, Deserialize)]
+pub enum KnockbackMode {
+    // Grows with the target's percent and shrinks with their weight (see GDD)
+    #[default]
+    Scaling,
+    // Always the base knockback, e.g. for jabs and multi-hit moves
+    Set,
+    // Grows with the target's percent, but ignores their weight
+    WeightIndependent,
+}
+
+impl KnockbackMode {
+    pub fn launch_speed(
+        &self,
+        base_knockback: f32,
+        scale_knockback: f32,
+        percent: f32,
+        weight: f32,
+    ) -> f32 {
+        match self {
+            Self::Scaling => weight.recip() * (base_knockback + (scale_knockback * percent) * 0.01),
+            Self::Set => base_knockback,
+            Self::WeightIndependent => base_knockback + (scale_knockback * percent) * 0.01,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
 pub enum KnockbackAngle {
     Fixed(f32), // Degrees, CW from positive y-axis (12 o'clock)
+    // Directly away from the hitbox
+    Away,
+    // Away, but at least this many degrees above the horizontal
+    UpAndAway(f32),
+    // Low for weak hits on grounded targets, diagonal otherwise
+    Sakurai,
 }
 
-#[derive(Component, Default, Clone, Copy, Debug)]
+// Grounded targets launched at a non-upward angle slower than this stay on the ground (see GDD)
+pub const GROUNDED_LAUNCH_THRESHOLD: f32 = 6.0;
+// Degrees above the horizontal
+const SAKURAI_AIRBORNE_ANGLE: f32 = 45.0;
+const SAKURAI_GROUNDED_MAX_ANGLE: f32 = 40.0;
+// Grounded Sakurai angles ramp up from horizontal between these launch speeds
+const SAKURAI_GROUNDED_MIN_SPEED: f32 = 4.0;
+const SAKURAI_GROUNDED_MAX_SPEED: f32 = 10.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Launch {
+    Airborne(Vec2),
+    // Grounded hitstun, sliding along the ground
+    Grounded(Vec2),
+}
+
+/*
+Launch velocity of a hit. Fixed angles point towards `side`,
+`source` and `target` are the positions of the hitbox and the fighter being hit.
+*/
+pub fn launch(
+    angle: &KnockbackAngle,
+    launch_speed: f32,
+    side: LeftRight,
+    source: Vec2,
+    target: Vec2,
+    grounded: bool,
+) -> Launch {
+    let mirror = Vec2::new(side.get_sign(), 1.0);
+    let away = (target - source)
+        .try_normalize()
+        .unwrap_or(Vec2::Y);
+    let direction = match angle {
+        // Converting CW degrees from 12 o'clock => standard form
+        KnockbackAngle::Fixed(theta) => {
+            Vec2::from_angle(0.5 * PI - theta.to_radians()) * mirror
+        }
+        KnockbackAngle::Away => away,
+        KnockbackAngle::UpAndAway(min_elevation) => up_and_away(away, *min_elevation),
+        KnockbackAngle::Sakurai => {
+            Vec2::from_angle(sakurai_elevation(launch_speed, grounded).to_radians()) * mirror
+        }
+    };
+    if !grounded || direction.y > 0.0 {
+        return Launch::Airborne(direction * launch_speed);
+    }
+    if launch_speed < GROUNDED_LAUNCH_THRESHOLD {
+        return Launch::Grounded(Vec2::new(direction.x * launch_speed, 0.0));
+    }
+    // Bounce off the ground at half speed
+    let flipped = if direction.y == 0.0 {
+        // Launched a little upwards so as to not land immediately
+        Vec2::from_angle(direction.x.signum() * 1.0_f32.to_radians()).rotate(direction)
+    } else {
+        Vec2::new(direction.x, -direction.y)
+    };
+    Launch::Airborne(flipped * 0.5 * launch_speed)
+}
+
+/*
+Uniformly maps the elevation of an away angle from [-90, 90] to [min, 90] degrees, so that
+an almost straight down angle becomes the minimum elevation (see GDD)
+*/
+fn up_and_away(away: Vec2, min_elevation: f32) -> Vec2 {
+    if away.x == 0.0 {
+        return Vec2::Y;
+    }
+    let elevation = away.y.atan2(away.x.abs()).to_degrees();
+    let elevation = min_elevation + (elevation + 90.0) * (90.0 - min_elevation) / 180.0;
+    let direction = Vec2::from_angle(elevation.to_radians());
+    Vec2::new(direction.x * away.x.signum(), direction.y)
+}
+
+/*
+Side that a hit launches its target towards. `attacker_x` is the position of the hitbox's
+owner, which can be the hitbox group itself
+*/
+pub fn knockback_side(
+    direction: &KnockbackDirection,
+    reverse_hit: bool,
+    facing: Option<LeftRight>,
+    velocity: Option<Vec2>,
+    attacker_x: f32,
+    target_x: f32,
+) -> LeftRight {
+    let forward = match direction {
+        KnockbackDirection::Facing => facing,
+        KnockbackDirection::Velocity => velocity
+            .filter(|velocity| velocity.x != 0.0)
+            .map(|velocity| {
+                if velocity.x < 0.0 {
+                    LeftRight::Left
+                } else {
+                    LeftRight::Right
+                }
+            }),
+    }
+    // Away from the attacker when there's nothing else to go by, e.g. for stage hazards
+    .unwrap_or(if target_x < attacker_x {
+        LeftRight::Left
+    } else {
+        LeftRight::Right
+    });
+    let behind = (target_x - attacker_x) * forward.get_sign() < 0.0;
+    if reverse_hit && behind {
+        forward.flip()
+    } else {
+        forward
+    }
+}
+
+// Degrees above the horizontal
+fn sakurai_elevation(launch_speed: f32, grounded: bool) -> f32 {
+    if !grounded {
+        return SAKURAI_AIRBORNE_ANGLE;
+    }
+    let t = (launch_speed - SAKURAI_GROUNDED_MIN_SPEED)
+        / (SAKURAI_GROUNDED_MAX_SPEED - SAKURAI_GROUNDED_MIN_SPEED);
+    SAKURAI_GROUNDED_MAX_ANGLE * t.clamp(0.0, 1.0)
+}
+
+#[derive(Component, Default, Clone, Debug, Deserialize)]
 pub struct Hitbox {
     pub shape: Shape,
     pub purpose: HitboxPurpose,
+    // Higher priority hitboxes are chosen first when several overlap, e.g. sweetspots
+    #[serde(default)]
+    pub priority: Option<u8>,
+    // Also covers everywhere the hitbox passed through since the last frame
+    #[serde(default)]
+    pub swept: bool,
+}
+
+// Global transform of a swept hitbox when overlaps were last detected
+#[derive(Component)]
+pub struct PreviousTransform(pub Transform);
+
+impl Hitbox {
+    fn is_body(&self) -> bool {
+        matches!(self.purpose, HitboxPurpose::Body)
+    }
 }
 
 #[derive(Bundle, Default)]
@@ -259,17 +805,73 @@ pub struct HitboxBundle {
     pub transform: TransformBundle,
 }
 
+#[derive(Clone, Copy, Debug)]
+struct GroupHit {
+    // How many times the other group had refreshed when the hit happened
+    other_refreshes: u32,
+    // Ignored until the next refresh if there is no rehit rate
+    frames_until_rehit: Option<FrameNumber>,
+}
+
+/*
+Once two groups collide, further collisions between them are ignored until
+either group refreshes (see GDD)
+*/
 #[derive(Component, Default)]
 pub struct HitboxGroup {
     ignored: HashSet<Entity>,
+    hits: HashMap<Entity, GroupHit>,
+    refreshes: u32,
+    // Frames after a hit before the same group can be hit again
+    pub rehit_rate: Option<FrameNumber>,
 }
 
 impl HitboxGroup {
     pub fn ignoring(entity: &Entity) -> Self {
         HitboxGroup {
             ignored: HashSet::from([*entity]),
+            ..Default::default()
         }
     }
+
+    pub fn with_rehit_rate(mut self, rehit_rate: Option<FrameNumber>) -> Self {
+        self.rehit_rate = rehit_rate;
+        self
+    }
+
+    // Forget every group this one has hit, e.g. for the next hit of a multi-hit move
+    pub fn refresh(&mut self) {
+        self.hits.clear();
+        self.refreshes += 1;
+    }
+
+    fn has_hit(&self, other: Entity, other_refreshes: u32) -> bool {
+        self.ignored.contains(&other)
+            || self
+                .hits
+                .get(&other)
+                .is_some_and(|hit| hit.other_refreshes == other_refreshes)
+    }
+
+    fn record_hit(
+        &mut self,
+        other: Entity,
+        other_refreshes: u32,
+        frames_until_rehit: Option<FrameNumber>,
+    ) {
+        self.hits.insert(
+            other,
+            GroupHit {
+                other_refreshes,
+                frames_until_rehit,
+            },
+        );
+    }
+
+    fn forget(&mut self, other: &Entity) {
+        self.ignored.remove(other);
+        self.hits.remove(other);
+    }
 }
 
 #[derive(Bundle, Default)]
@@ -291,6 +893,51 @@ fn despawn_empty_hitbox_groups(
     }
 }
 
+fn count_down_rehits(mut q: Query<&mut HitboxGroup>) {
+    for mut group in q.iter_mut() {
+        group.hits.retain(|_, hit| match hit.frames_until_rehit.as_mut() {
+            Some(frames) => {
+                *frames = frames.saturating_sub(1);
+                *frames > 0
+            }
+            None => true,
+        });
+    }
+}
+
+// Removed groups are forgotten, so that their entities can't be ignored by mistake
+fn forget_removed_hitbox_groups(
+    mut q: Query<&mut HitboxGroup>,
+    mut removed: RemovedComponents<HitboxGroup>,
+) {
+    for entity in removed.read() {
+        for mut group in q.iter_mut() {
+            group.forget(&entity);
+        }
+    }
+}
+
+// Triangle fan over the polygon's vertices
+fn convex_polygon_mesh(vertices: &[Vec2]) -> Mesh {
+    let positions = vertices
+        .iter()
+        .map(|vertex| [vertex.x, vertex.y, 0.0])
+        .collect_vec();
+    let normals = vec![[0.0, 0.0, 1.0]; vertices.len()];
+    let uvs = vec![[0.0, 0.0]; vertices.len()];
+    let indices = (1..vertices.len().saturating_sub(1) as u32)
+        .flat_map(|i| [0, i, i + 1])
+        .collect_vec();
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
 fn add_mesh_to_hitboxes(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -298,17 +945,22 @@ fn add_mesh_to_hitboxes(
     query: Query<(Entity, &Hitbox, &Transform), Without<Mesh2dHandle>>,
 ) {
     for (e, hitbox, transform) in query.iter() {
-        let mesh_handle = match hitbox.shape {
-            Shape::Circle(r) => Mesh2dHandle(meshes.add(Circle { radius: r })),
+        let mesh_handle = match &hitbox.shape {
+            Shape::Circle(r) => Mesh2dHandle(meshes.add(Circle { radius: *r })),
             Shape::Pill {
                 major_radius,
                 minor_radius,
             } => {
-                let (r, a, b) = get_pill_geometric_data(major_radius, minor_radius, transform);
+                let (r, a, b) = get_pill_geometric_data(*major_radius, *minor_radius, transform);
                 let length = (a - b).length();
                 let mesh = Capsule2d::new(r, length);
                 Mesh2dHandle(meshes.add(mesh))
             }
+            Shape::Rect {
+                half_width,
+                half_height,
+            } => Mesh2dHandle(meshes.add(Rectangle::new(2.0 * half_width, 2.0 * half_height))),
+            Shape::Polygon(vertices) => Mesh2dHandle(meshes.add(convex_polygon_mesh(vertices))),
         };
         let colour = match hitbox.purpose {
             HitboxPurpose::Body => Color::linear_rgba(0.05, 0.9, 0.05, 0.5),
@@ -333,43 +985,126 @@ fn add_mesh_to_hitboxes(
 #[allow(dead_code)]
 pub struct HitboxCollision {
     pub target: Entity,
+    // The owner of the other group, or the group itself
+    pub other: Entity,
+    pub target_group: Entity,
+    pub other_group: Entity,
     pub target_hitbox: Hitbox,
     pub other_hitbox: Hitbox,
+    pub target_transform: Transform,
     pub other_transform: Transform,
     pub nearest_pass: NearestPass,
 }
 
+/*
+Ordering of overlapping hitbox pairs. Highest priority in either hitbox first, then the lower
+of the two priorities, then the nearest pass (see GDD)
+*/
+fn overlap_order(
+    (h1_a, h2_a, pass_a): (&Hitbox, &Hitbox, &NearestPass),
+    (h1_b, h2_b, pass_b): (&Hitbox, &Hitbox, &NearestPass),
+) -> std::cmp::Ordering {
+    let highest_a = h1_a.priority.max(h2_a.priority);
+    let highest_b = h1_b.priority.max(h2_b.priority);
+    let lowest_a = h1_a.priority.min(h2_a.priority);
+    let lowest_b = h1_b.priority.min(h2_b.priority);
+    highest_a
+        .cmp(&highest_b)
+        .then(lowest_a.cmp(&lowest_b))
+        .then(pass_b.cmp(pass_a))
+}
+
+/*
+Sweep and prune: pairs of groups whose bounding boxes overlap, so that only those need the
+exact distance test
+*/
+fn broadphase_pairs(mut bounds: Vec<(Entity, Rect)>) -> Vec<[Entity; 2]> {
+    bounds.sort_by(|(_, a), (_, b)| a.min.x.total_cmp(&b.min.x));
+    let mut pairs = Vec::new();
+    for (i, (entity_1, bounds_1)) in bounds.iter().enumerate() {
+        for (entity_2, bounds_2) in bounds[i + 1..].iter() {
+            if bounds_2.min.x > bounds_1.max.x {
+                break;
+            }
+            if bounds_2.min.y <= bounds_1.max.y && bounds_1.min.y <= bounds_2.max.y {
+                pairs.push([*entity_1, *entity_2]);
+            }
+        }
+    }
+    pairs
+}
+
 fn detect_hitbox_overlaps(
     mut q_hitbox_groups: Query<(Entity, &Children, Option<&Parent>, &mut HitboxGroup)>,
-    q_hitboxes: Query<(&Hitbox, &GlobalTransform)>,
+    q_hitboxes: Query<(&Hitbox, &GlobalTransform, Option<&PreviousTransform>)>,
+    q_intangible: Query<(), With<Intangible>>,
     mut ev_hitbox_collision: EventWriter<HitboxCollision>,
 ) {
-    let mut iter = q_hitbox_groups.iter_combinations_mut();
-    while let Some(
-        [(group_id_1, children_1, parent_1, group_1), (group_id_2, children_2, parent_2, group_2)],
-    ) = iter.fetch_next()
-    {
-        if group_1.ignored.contains(&group_id_2) || group_2.ignored.contains(&group_id_1) {
+    let group_bounds = q_hitbox_groups
+        .iter()
+        .filter_map(|(group_id, children, ..)| {
+            children
+                .iter()
+                .filter_map(|child_id| q_hitboxes.get(*child_id).ok())
+                .map(|(hitbox, global_transform, previous)| {
+                    hitbox.shape.swept_bounds(
+                        &global_transform.compute_transform(),
+                        previous.map(|previous| &previous.0),
+                    )
+                })
+                .reduce(|bounds_1, bounds_2| bounds_1.union(bounds_2))
+                .map(|bounds| (group_id, bounds))
+        })
+        .collect_vec();
+    for pair in broadphase_pairs(group_bounds) {
+        let Ok(
+            [
+                (group_id_1, children_1, parent_1, mut group_1),
+                (group_id_2, children_2, parent_2, mut group_2),
+            ],
+        ) = q_hitbox_groups.get_many_mut(pair)
+        else {
+            continue;
+        };
+        if group_1.has_hit(group_id_2, group_2.refreshes)
+            || group_2.has_hit(group_id_1, group_1.refreshes)
+        {
             continue;
         }
+        let target_1 = parent_1
+            .map(Parent::get)
+            .unwrap_or(group_id_1);
+        let target_2 = parent_2
+            .map(Parent::get)
+            .unwrap_or(group_id_2);
+        // Intangible bodies can't be hit at all
+        let intangible_1 = q_intangible.contains(target_1);
+        let intangible_2 = q_intangible.contains(target_2);
         let hitboxes_1 = children_1
             .iter()
-            .filter_map(|child_id| q_hitboxes.get(*child_id).ok());
+            .filter_map(|child_id| q_hitboxes.get(*child_id).ok())
+            .filter(|(hitbox, ..)| !(intangible_1 && hitbox.is_body()));
         let hitboxes_2 = children_2
             .iter()
-            .filter_map(|child_id| q_hitboxes.get(*child_id).ok());
+            .filter_map(|child_id| q_hitboxes.get(*child_id).ok())
+            .filter(|(hitbox, ..)| !(intangible_2 && hitbox.is_body()));
         let maybe_overlap = hitboxes_1
             .cartesian_product(hitboxes_2)
-            .map(|((h1, gt1), (h2, gt2))| {
+            .map(|((h1, gt1, previous_1), (h2, gt2, previous_2))| {
                 /*
                 These calls to compute_transform could theoretically fail,
                 but this should never happen in practice.
                  */
-                (h1, gt1.compute_transform(), h2, gt2.compute_transform())
-            })
-            .map(|(h1, t1, h2, t2)| {
+                let (t1, t2) = (gt1.compute_transform(), gt2.compute_transform());
                 (
-                    Shape::nearest_pass(&h1.shape, &t1, &h2.shape, &t2),
+                    Shape::swept_nearest_pass(
+                        &h1.shape,
+                        &t1,
+                        previous_1.map(|previous| &previous.0),
+                        &h2.shape,
+                        &t2,
+                        previous_2.map(|previous| &previous.0),
+                    ),
                     h1,
                     t1,
                     h2,
@@ -377,30 +1112,41 @@ fn detect_hitbox_overlaps(
                 )
             })
             .filter(|(pass, ..)| pass.is_collision())
-            .reduce(|pass_1, pass_2| if pass_1.0 < pass_2.0 { pass_1 } else { pass_2 });
+            .max_by(|(pass_a, h1_a, _, h2_a, _), (pass_b, h1_b, _, h2_b, _)| {
+                overlap_order((h1_a, h2_a, pass_a), (h1_b, h2_b, pass_b))
+            });
         if let Some((nearest_pass, h1, t1, h2, t2)) = maybe_overlap {
+            let frames_until_rehit = match (group_1.rehit_rate, group_2.rehit_rate) {
+                (Some(rate_1), Some(rate_2)) => Some(rate_1.min(rate_2)),
+                (rate_1, rate_2) => rate_1.or(rate_2),
+            };
+            let (refreshes_1, refreshes_2) = (group_1.refreshes, group_2.refreshes);
             debug!(
                 "Overlap between {:?}, {:?}: {:?}",
                 group_id_1, group_id_2, nearest_pass,
             );
-            // group_1.ignored.insert(e2);
-            // group_2.ignored.insert(e1);
+            group_1.record_hit(group_id_2, refreshes_2, frames_until_rehit);
+            group_2.record_hit(group_id_1, refreshes_1, frames_until_rehit);
 
             ev_hitbox_collision.send(HitboxCollision {
-                target: parent_1
-                    .map(Parent::get)
-                    .unwrap_or(group_id_1),
-                target_hitbox: *h1,
-                other_hitbox: *h2,
+                target: target_1,
+                other: target_2,
+                target_group: group_id_1,
+                other_group: group_id_2,
+                target_hitbox: h1.clone(),
+                other_hitbox: h2.clone(),
+                target_transform: t1,
                 other_transform: t2,
                 nearest_pass,
             });
             ev_hitbox_collision.send(HitboxCollision {
-                target: parent_2
-                    .map(Parent::get)
-                    .unwrap_or(group_id_2),
-                target_hitbox: *h2,
-                other_hitbox: *h1,
+                target: target_2,
+                other: target_1,
+                target_group: group_id_2,
+                other_group: group_id_1,
+                target_hitbox: h2.clone(),
+                other_hitbox: h1.clone(),
+                target_transform: t2,
                 other_transform: t1,
                 nearest_pass,
             });
@@ -408,6 +1154,30 @@ fn detect_hitbox_overlaps(
     }
 }
 
+fn remember_swept_hitbox_transforms(
+    mut commands: Commands,
+    mut q: Query<(Entity, &Hitbox, Ref<GlobalTransform>, Option<&mut PreviousTransform>)>,
+) {
+    for (entity, hitbox, global_transform, previous) in q.iter_mut() {
+        if !hitbox.swept {
+            continue;
+        }
+        // New hitboxes haven't been moved into place yet
+        if global_transform.is_added() {
+            continue;
+        }
+        let transform = global_transform.compute_transform();
+        match previous {
+            Some(mut previous) => previous.0 = transform,
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(PreviousTransform(transform));
+            }
+        }
+    }
+}
+
 pub struct HitboxPlugin;
 
 impl Plugin for HitboxPlugin {
@@ -416,10 +1186,691 @@ impl Plugin for HitboxPlugin {
             .add_systems(
                 FixedUpdate,
                 (
-                    detect_hitbox_overlaps.after(FighterEventSet::Act),
+                    (
+                        forget_removed_hitbox_groups,
+                        count_down_rehits,
+                        detect_hitbox_overlaps,
+                        remember_swept_hitbox_transforms,
+                    )
+                        .chain()
+                        .after(FighterEventSet::Act),
                     despawn_empty_hitbox_groups,
                 ),
             )
             .add_event::<HitboxCollision>();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate test;
+
+    use std::cmp::Ordering;
+
+    use bevy::ecs::system::RunSystemOnce;
+    use proptest::prelude::*;
+    use test::Bencher;
+
+    use super::*;
+
+    fn hitbox(priority: Option<u8>) -> Hitbox {
+        Hitbox {
+            priority,
+            ..default()
+        }
+    }
+
+    fn pass(distance: f32) -> NearestPass {
+        NearestPass {
+            midpoint: Vec2::ZERO,
+            distance,
+        }
+    }
+
+    #[test]
+    fn highest_priority_in_either_hitbox_first() {
+        let (low, mid, high) = (hitbox(Some(1)), hitbox(Some(2)), hitbox(Some(3)));
+        // The highest priority wins even if it's the other hitbox's and the pass is further
+        assert_eq!(
+            overlap_order((&low, &high, &pass(-1.0)), (&mid, &mid, &pass(-5.0))),
+            Ordering::Greater
+        );
+        assert_eq!(
+            overlap_order((&high, &low, &pass(-1.0)), (&low, &high, &pass(-5.0))),
+            Ordering::Less
+        );
+        // Ties are broken with the other priority in each pair
+        assert_eq!(
+            overlap_order((&high, &mid, &pass(-1.0)), (&low, &high, &pass(-5.0))),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn unset_priority_is_lowest() {
+        let (none, low) = (hitbox(None), hitbox(Some(0)));
+        assert_eq!(
+            overlap_order((&none, &low, &pass(-1.0)), (&none, &none, &pass(-5.0))),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn sweetspot_wins_over_nearer_sourspot() {
+        let body = hitbox(None);
+        let (sweetspot, sourspot) = (hitbox(Some(1)), hitbox(Some(0)));
+        assert_eq!(
+            overlap_order((&body, &sweetspot, &pass(-1.0)), (&body, &sourspot, &pass(-5.0))),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn equal_priorities_prefer_the_nearest_pass() {
+        let (body, sourspot) = (hitbox(None), hitbox(Some(0)));
+        assert_eq!(
+            overlap_order((&body, &sourspot, &pass(-5.0)), (&body, &sourspot, &pass(-1.0))),
+            Ordering::Greater
+        );
+    }
+
+    // Number of hits detected between a body and an overlapping damaging hitbox
+    fn hits_on_body(intangible: bool) -> usize {
+        let mut world = World::new();
+        world.init_resource::<Events<HitboxCollision>>();
+        let damage = HitboxPurpose::Damage {
+            percent: 10.0,
+            base_knockback: 5.0,
+            scale_knockback: 1.0,
+            angle: KnockbackAngle::Fixed(45.0),
+            knockback: default(),
+            direction: default(),
+            reverse_hit: false,
+        };
+        let target = world.spawn_empty().id();
+        if intangible {
+            world
+                .entity_mut(target)
+                .insert(Intangible);
+        }
+        for (owner, purpose) in [(Some(target), HitboxPurpose::Body), (None, damage)] {
+            let hitbox = world
+                .spawn((
+                    Hitbox {
+                        shape: Shape::Circle(10.0),
+                        purpose,
+                        ..default()
+                    },
+                    GlobalTransform::default(),
+                ))
+                .id();
+            let mut group = world.spawn(HitboxGroup::default());
+            group.push_children(&[hitbox]);
+            if let Some(owner) = owner {
+                group.set_parent(owner);
+            }
+        }
+        world.run_system_once(detect_hitbox_overlaps);
+        world
+            .resource::<Events<HitboxCollision>>()
+            .len()
+    }
+
+    #[test]
+    fn bodies_are_hit() {
+        // One collision for each group
+        assert_eq!(hits_on_body(false), 2);
+    }
+
+    #[test]
+    fn intangible_bodies_are_not_hit() {
+        assert_eq!(hits_on_body(true), 0);
+    }
+
+    // Two overlapping groups, the first of which has the given rehit rate
+    fn spawn_overlapping_groups(world: &mut World, rehit_rate: Option<FrameNumber>) -> [Entity; 2] {
+        world.init_resource::<Events<HitboxCollision>>();
+        [rehit_rate, None].map(|rehit_rate| {
+            let hitbox = world
+                .spawn((
+                    Hitbox {
+                        shape: Shape::Circle(10.0),
+                        ..default()
+                    },
+                    GlobalTransform::default(),
+                ))
+                .id();
+            world
+                .spawn(HitboxGroup::default().with_rehit_rate(rehit_rate))
+                .push_children(&[hitbox])
+                .id()
+        })
+    }
+
+    // Runs a frame of hit detection, returning whether the groups hit each other
+    fn detect_hit(world: &mut World) -> bool {
+        world.run_system_once(count_down_rehits);
+        world.run_system_once(detect_hitbox_overlaps);
+        world
+            .resource_mut::<Events<HitboxCollision>>()
+            .drain()
+            .count()
+            > 0
+    }
+
+    #[test]
+    fn groups_hit_each_other_once() {
+        let mut world = World::new();
+        spawn_overlapping_groups(&mut world, None);
+        assert!(detect_hit(&mut world));
+        assert!(!detect_hit(&mut world));
+        assert!(!detect_hit(&mut world));
+    }
+
+    #[test]
+    fn refresh_allows_one_more_hit() {
+        let mut world = World::new();
+        let [group, _] = spawn_overlapping_groups(&mut world, None);
+        assert!(detect_hit(&mut world));
+        world
+            .get_mut::<HitboxGroup>(group)
+            .unwrap()
+            .refresh();
+        assert!(detect_hit(&mut world));
+        assert!(!detect_hit(&mut world));
+    }
+
+    #[test]
+    fn rehit_rate_allows_another_hit_after_its_frames() {
+        const REHIT_RATE: FrameNumber = 3;
+        let mut world = World::new();
+        spawn_overlapping_groups(&mut world, Some(REHIT_RATE));
+        assert!(detect_hit(&mut world));
+        for _ in 1..REHIT_RATE {
+            assert!(!detect_hit(&mut world));
+        }
+        assert!(detect_hit(&mut world));
+    }
+
+    #[test]
+    fn despawned_groups_are_forgotten() {
+        let mut world = World::new();
+        let [group, other] = spawn_overlapping_groups(&mut world, None);
+        let ignored = world.spawn(HitboxGroup::default()).id();
+        *world
+            .get_mut::<HitboxGroup>(group)
+            .unwrap() = HitboxGroup::ignoring(&ignored);
+        assert!(detect_hit(&mut world));
+        world.entity_mut(other).despawn_recursive();
+        world.entity_mut(ignored).despawn();
+        world.run_system_once(forget_removed_hitbox_groups);
+        let group = world.get::<HitboxGroup>(group).unwrap();
+        assert!(group.hits.is_empty());
+        assert!(group.ignored.is_empty());
+    }
+
+    fn airborne_velocity(launched: Launch) -> Vec2 {
+        match launched {
+            Launch::Airborne(velocity) => velocity,
+            Launch::Grounded(velocity) => panic!("Not launched: {:?}", velocity),
+        }
+    }
+
+    #[test]
+    fn strong_sideways_hits_on_the_ground_launch_slightly_upwards() {
+        let sideways = KnockbackAngle::Fixed(90.0);
+        for side in [LeftRight::Left, LeftRight::Right] {
+            let velocity = airborne_velocity(launch(
+                &sideways,
+                10.0,
+                side,
+                Vec2::ZERO,
+                Vec2::ZERO,
+                true,
+            ));
+            let one_degree = 1.0_f32.to_radians();
+            let expected = Vec2::new(side.get_sign() * one_degree.cos(), one_degree.sin());
+            assert!(velocity.abs_diff_eq(expected * 5.0, 1e-4));
+        }
+    }
+
+    #[test]
+    fn strong_downward_hits_on_the_ground_bounce() {
+        let velocity = airborne_velocity(launch(
+            &KnockbackAngle::Fixed(135.0),
+            10.0,
+            LeftRight::Right,
+            Vec2::ZERO,
+            Vec2::ZERO,
+            true,
+        ));
+        assert!(velocity.abs_diff_eq(Vec2::new(1.0, 1.0).normalize() * 5.0, 1e-4));
+    }
+
+    #[test]
+    fn weak_downward_hits_on_the_ground_push_along_it() {
+        let launched = launch(
+            &KnockbackAngle::Fixed(135.0),
+            GROUNDED_LAUNCH_THRESHOLD - 1.0,
+            LeftRight::Left,
+            Vec2::ZERO,
+            Vec2::ZERO,
+            true,
+        );
+        let Launch::Grounded(velocity) = launched else {
+            panic!("Launched: {:?}", launched);
+        };
+        assert_eq!(velocity.y, 0.0);
+        assert!(velocity.x < 0.0);
+    }
+
+    #[test]
+    fn up_and_away_turns_straight_down_into_straight_up() {
+        assert_eq!(up_and_away(Vec2::NEG_Y, 20.0), Vec2::Y);
+        assert_eq!(up_and_away(Vec2::Y, 20.0), Vec2::Y);
+    }
+
+    #[test]
+    fn up_and_away_keeps_the_minimum_elevation() {
+        let below = Vec2::new(1.0, -1000.0).normalize();
+        let direction = up_and_away(below, 20.0);
+        assert!((direction.y.atan2(direction.x).to_degrees() - 20.0).abs() < 0.1);
+        // Pointing away horizontally on either side
+        assert!(up_and_away(Vec2::NEG_X, 20.0).x < 0.0);
+        assert!(up_and_away(Vec2::X, 20.0).x > 0.0);
+    }
+
+    #[test]
+    fn sakurai_angle_limits() {
+        assert_eq!(sakurai_elevation(1.0, false), SAKURAI_AIRBORNE_ANGLE);
+        assert_eq!(sakurai_elevation(100.0, false), SAKURAI_AIRBORNE_ANGLE);
+        assert_eq!(sakurai_elevation(SAKURAI_GROUNDED_MIN_SPEED - 1.0, true), 0.0);
+        assert_eq!(
+            sakurai_elevation(SAKURAI_GROUNDED_MAX_SPEED + 1.0, true),
+            SAKURAI_GROUNDED_MAX_ANGLE
+        );
+        let halfway = 0.5 * (SAKURAI_GROUNDED_MIN_SPEED + SAKURAI_GROUNDED_MAX_SPEED);
+        let elevation = sakurai_elevation(halfway, true);
+        assert!((elevation - 0.5 * SAKURAI_GROUNDED_MAX_ANGLE).abs() < 1e-4);
+    }
+
+    #[test]
+    fn knockback_follows_the_attacker() {
+        let facing = KnockbackDirection::Facing;
+        let left = Some(LeftRight::Left);
+        assert_eq!(knockback_side(&facing, false, left, None, 0.0, 10.0), LeftRight::Left);
+        // Reverse hits send targets behind the attacker backwards
+        assert_eq!(knockback_side(&facing, true, left, None, 0.0, 10.0), LeftRight::Right);
+        assert_eq!(knockback_side(&facing, true, left, None, 0.0, -10.0), LeftRight::Left);
+        let moving = KnockbackDirection::Velocity;
+        let velocity = Some(Vec2::new(-3.0, 1.0));
+        assert_eq!(knockback_side(&moving, false, None, velocity, 0.0, 10.0), LeftRight::Left);
+    }
+
+    #[test]
+    fn knockback_without_a_direction_is_away_from_the_attacker() {
+        let facing = KnockbackDirection::Facing;
+        assert_eq!(knockback_side(&facing, false, None, None, 0.0, -10.0), LeftRight::Left);
+        assert_eq!(knockback_side(&facing, false, None, None, 0.0, 10.0), LeftRight::Right);
+        let moving = KnockbackDirection::Velocity;
+        let still = Some(Vec2::ZERO);
+        assert_eq!(knockback_side(&moving, true, None, still, 5.0, 0.0), LeftRight::Left);
+    }
+
+    #[test]
+    fn scaling_knockback_grows_with_percent_and_shrinks_with_weight() {
+        let scaling = KnockbackMode::Scaling;
+        assert_eq!(scaling.launch_speed(2.0, 10.0, 50.0, 1.0), 7.0);
+        assert_eq!(scaling.launch_speed(2.0, 10.0, 50.0, 2.0), 3.5);
+    }
+
+    #[test]
+    fn set_knockback_is_always_the_base_knockback() {
+        let set = KnockbackMode::Set;
+        assert_eq!(set.launch_speed(2.0, 10.0, 0.0, 1.0), 2.0);
+        assert_eq!(set.launch_speed(2.0, 10.0, 150.0, 0.5), 2.0);
+    }
+
+    #[test]
+    fn weight_independent_knockback_ignores_weight() {
+        let weight_independent = KnockbackMode::WeightIndependent;
+        assert_eq!(weight_independent.launch_speed(2.0, 10.0, 50.0, 1.0), 7.0);
+        assert_eq!(weight_independent.launch_speed(2.0, 10.0, 50.0, 2.0), 7.0);
+        assert_eq!(weight_independent.launch_speed(2.0, 10.0, 0.0, 2.0), 2.0);
+    }
+
+    // Reference for the broadphase: every pair of groups whose bounds overlap
+    fn all_pairs(bounds: &[(Entity, Rect)]) -> Vec<[Entity; 2]> {
+        bounds
+            .iter()
+            .tuple_combinations()
+            .filter(|((_, bounds_1), (_, bounds_2))| !bounds_1.intersect(*bounds_2).is_empty())
+            .map(|((entity_1, _), (entity_2, _))| [*entity_1, *entity_2])
+            .collect()
+    }
+
+    fn sorted_pairs(pairs: Vec<[Entity; 2]>) -> HashSet<[Entity; 2]> {
+        pairs
+            .into_iter()
+            .map(|mut pair| {
+                pair.sort();
+                pair
+            })
+            .collect()
+    }
+
+    fn with_entities(rects: Vec<Rect>) -> Vec<(Entity, Rect)> {
+        rects
+            .into_iter()
+            .enumerate()
+            .map(|(i, rect)| (Entity::from_raw(i as u32), rect))
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn broadphase_keeps_every_overlapping_pair(
+            rects in prop::collection::vec(
+                (-500.0_f32..500.0, -500.0_f32..500.0, 0.0_f32..200.0, 0.0_f32..200.0)
+                    .prop_map(|(x, y, width, height)| Rect::new(x, y, x + width, y + height)),
+                0..40,
+            )
+        ) {
+            let bounds = with_entities(rects);
+            let pruned = sorted_pairs(broadphase_pairs(bounds.clone()));
+            prop_assert!(sorted_pairs(all_pairs(&bounds)).is_subset(&pruned));
+        }
+    }
+
+    const BENCH_GROUPS: u32 = 200;
+
+    // Hitboxes of a group, with their current and previous transforms
+    type BenchGroup = Vec<(Shape, Transform, Transform)>;
+
+    // Groups of a circle, a pill and a polygon spread out over a stage, with a few overlaps. Every
+    // group has moved since the previous frame, so the hitboxes are swept
+    fn scattered_groups() -> Vec<BenchGroup> {
+        let triangle = Shape::Polygon(convex_hull(vec![
+            Vec2::new(-10.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(0.0, 20.0),
+        ]));
+        let pill = Shape::Pill {
+            major_radius: 15.0,
+            minor_radius: 6.0,
+        };
+        (0..BENCH_GROUPS)
+            .map(|i| {
+                let centre = Vec2::new((i as f32 * 61.8) % 2000.0, (i as f32 * 38.2) % 1000.0);
+                let displacement = Vec2::new(8.0, -4.0).rotate(Vec2::from_angle(i as f32));
+                [
+                    (Shape::Circle(10.0), Vec2::new(0.0, 25.0)),
+                    (pill.clone(), Vec2::ZERO),
+                    (triangle.clone(), Vec2::new(15.0, -20.0)),
+                ]
+                .into_iter()
+                .map(|(shape, offset)| {
+                    let transform = Transform::from_translation((centre + offset).extend(0.0))
+                        .with_rotation(Quat::from_rotation_z(i as f32));
+                    let previous = transform.with_translation(
+                        transform.translation - displacement.extend(0.0),
+                    );
+                    (shape, transform, previous)
+                })
+                .collect()
+            })
+            .collect()
+    }
+
+    fn group_bounds(groups: &[BenchGroup]) -> Vec<(Entity, Rect)> {
+        with_entities(
+            groups
+                .iter()
+                .map(|group| {
+                    group
+                        .iter()
+                        .map(|(shape, transform, previous)| {
+                            shape.swept_bounds(transform, Some(previous))
+                        })
+                        .reduce(|bounds_1, bounds_2| bounds_1.union(bounds_2))
+                        .expect("Group bounds")
+                })
+                .collect(),
+        )
+    }
+
+    // Nearest colliding pass between two groups' hitboxes, as in `detect_hitbox_overlaps`
+    fn narrow_phase(group_1: &BenchGroup, group_2: &BenchGroup) -> Option<NearestPass> {
+        group_1
+            .iter()
+            .cartesian_product(group_2.iter())
+            .map(|((s1, t1, previous_1), (s2, t2, previous_2))| {
+                Shape::swept_nearest_pass(s1, t1, Some(previous_1), s2, t2, Some(previous_2))
+            })
+            .filter(NearestPass::is_collision)
+            .min()
+    }
+
+    // Number of pairs of groups which collide, checking only the pairs from the broadphase
+    fn collisions_after_broadphase(groups: &[BenchGroup]) -> usize {
+        broadphase_pairs(group_bounds(groups))
+            .into_iter()
+            .filter(|[entity_1, entity_2]| {
+                let group = |entity: &Entity| &groups[entity.index() as usize];
+                narrow_phase(group(entity_1), group(entity_2)).is_some()
+            })
+            .count()
+    }
+
+    fn collisions_in_all_pairs(groups: &[BenchGroup]) -> usize {
+        groups
+            .iter()
+            .tuple_combinations()
+            .filter(|(group_1, group_2)| narrow_phase(group_1, group_2).is_some())
+            .count()
+    }
+
+    #[test]
+    fn broadphase_finds_every_collision() {
+        let groups = scattered_groups();
+        let collisions = collisions_in_all_pairs(&groups);
+        assert!(collisions > 0);
+        assert_eq!(collisions_after_broadphase(&groups), collisions);
+    }
+
+    #[bench]
+    fn bench_narrow_phase_after_broadphase(b: &mut Bencher) {
+        let groups = scattered_groups();
+        b.iter(|| collisions_after_broadphase(&groups));
+    }
+
+    #[bench]
+    fn bench_narrow_phase_on_all_pairs(b: &mut Bencher) {
+        let groups = scattered_groups();
+        b.iter(|| collisions_in_all_pairs(&groups));
+    }
+
+    #[test]
+    fn polygons_load_as_their_convex_hull() {
+        let shape: Shape =
+            ron::from_str("Polygon([(0.0, 0.0), (2.0, 2.0), (1.0, 0.5), (2.0, 0.0), (0.0, 2.0)])")
+                .unwrap();
+        let Shape::Polygon(vertices) = shape else {
+            panic!("Not a polygon: {:?}", shape);
+        };
+        assert_eq!(vertices.len(), 4);
+        assert!(!vertices.contains(&Vec2::new(1.0, 0.5)));
+    }
+
+    #[test]
+    fn degenerate_polygons_are_rejected() {
+        assert!(ron::from_str::<Shape>("Polygon([])").is_err());
+        assert!(ron::from_str::<Shape>("Polygon([(0.0, 0.0), (1.0, 1.0)])").is_err());
+        assert!(ron::from_str::<Shape>("Polygon([(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)])").is_err());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Polygon without vertices")]
+    fn empty_polygons_are_rejected() {
+        let empty = Shape::Polygon(vec![]);
+        let transform = Transform::IDENTITY;
+        Shape::nearest_pass(&empty, &transform, &Shape::Circle(5.0), &transform);
+    }
+
+    fn assert_distance(s1: &Shape, t1: &Transform, s2: &Shape, t2: &Transform, expected: f32) {
+        let distance = Shape::nearest_pass(s1, t1, s2, t2).distance();
+        assert!((distance - expected).abs() < 1e-4, "{} != {}", distance, expected);
+    }
+
+    const WIDE_RECT: Shape = Shape::Rect {
+        half_width: 10.0,
+        half_height: 5.0,
+    };
+
+    #[test]
+    fn gap_between_separated_rects() {
+        let apart = Transform::from_xyz(30.0, 0.0, 0.0);
+        assert_distance(&WIDE_RECT, &Transform::IDENTITY, &WIDE_RECT, &apart, 10.0);
+    }
+
+    #[test]
+    fn penetration_depth_of_overlapping_rects() {
+        let overlapping = Transform::from_xyz(15.0, 2.0, 0.0);
+        assert_distance(&WIDE_RECT, &Transform::IDENTITY, &WIDE_RECT, &overlapping, -5.0);
+    }
+
+    #[test]
+    fn circle_near_a_rect_corner_is_measured_to_the_corner() {
+        let square = Shape::Rect {
+            half_width: 10.0,
+            half_height: 10.0,
+        };
+        let diagonal = Transform::from_xyz(13.0, 14.0, 0.0);
+        assert_distance(&square, &Transform::IDENTITY, &Shape::Circle(2.0), &diagonal, 3.0);
+    }
+
+    #[test]
+    fn pill_overlapping_a_polygon() {
+        let pill = Shape::Pill {
+            major_radius: 10.0,
+            minor_radius: 2.0,
+        };
+        // The pill's core runs up the polygon, 1 from its left edge
+        let polygon = Shape::Polygon(vec![
+            Vec2::new(-1.0, -20.0),
+            Vec2::new(3.0, -20.0),
+            Vec2::new(5.0, 20.0),
+            Vec2::new(-1.0, 20.0),
+        ]);
+        assert_distance(&pill, &Transform::IDENTITY, &polygon, &Transform::IDENTITY, -3.0);
+    }
+
+    #[test]
+    fn rotated_rect_is_measured_along_its_rotation() {
+        let upright = Transform::from_rotation(Quat::from_rotation_z(0.5 * PI));
+        let beside = Transform::from_xyz(15.0, 0.0, 0.0);
+        assert_distance(&WIDE_RECT, &upright, &Shape::Circle(1.0), &beside, 9.0);
+    }
+
+    #[test]
+    fn fast_circles_hit_what_they_pass_over() {
+        // Moving 10 a frame with a radius of 5, the hurtbox is between where the circle was and is
+        let circle = Shape::Circle(5.0);
+        let previous = Transform::from_xyz(-5.0, 0.0, 0.0);
+        let current = Transform::from_xyz(5.0, 0.0, 0.0);
+        let hurtbox = Shape::Rect {
+            half_width: 0.5,
+            half_height: 0.5,
+        };
+        let hurtbox_transform = Transform::from_xyz(0.0, 3.0, 0.0);
+        let pass = |previous| {
+            Shape::swept_nearest_pass(
+                &circle,
+                &current,
+                previous,
+                &hurtbox,
+                &hurtbox_transform,
+                None,
+            )
+        };
+        assert!(pass(Some(&previous)).is_collision());
+        assert!(!pass(None).is_collision());
+    }
+
+    #[test]
+    fn pills_moving_along_their_core_only_hit_what_they_cover() {
+        let pill = Shape::Pill {
+            major_radius: 10.0,
+            minor_radius: 2.0,
+        };
+        let body = Shape::Circle(5.0);
+        let body_transform = Transform::from_xyz(0.0, 100.0, 0.0);
+        let pass = |current: &Transform, previous: &Transform| {
+            Shape::swept_nearest_pass(&pill, current, Some(previous), &body, &body_transform, None)
+                .distance()
+        };
+        let still = Transform::IDENTITY;
+        assert_eq!(pass(&still, &still), 83.0);
+        let moved_up = Transform::from_xyz(0.0, 80.0, 0.0);
+        assert_eq!(pass(&moved_up, &still), 3.0);
+    }
+
+    #[test]
+    fn circles_scale_with_the_vertical_scale() {
+        let circle = Shape::Circle(5.0);
+        let transform = Transform::from_scale(Vec3::new(-3.0, 2.0, 1.0));
+        let radius = 0.5 * circle.bounds(&transform).width();
+        let (_, core_radius) = circle.convex_core(&transform);
+        assert_eq!(radius, 10.0);
+        assert_eq!(core_radius, 10.0);
+        let (swept, _) = circle.swept_pieces(&transform, Some(&transform))[0].clone();
+        assert!(matches!(swept, Shape::Circle(r) if r == 10.0));
+    }
+
+    fn arb_shape() -> impl Strategy<Value = Shape> {
+        prop_oneof![
+            (1.0_f32..50.0).prop_map(Shape::Circle),
+            (1.0_f32..50.0, 1.0_f32..30.0).prop_map(|(major_radius, minor_radius)| {
+                Shape::Pill {
+                    major_radius,
+                    minor_radius,
+                }
+            }),
+            (1.0_f32..50.0, 1.0_f32..50.0).prop_map(|(half_width, half_height)| {
+                Shape::Rect {
+                    half_width,
+                    half_height,
+                }
+            }),
+            prop::collection::vec((-50.0_f32..50.0, -50.0_f32..50.0), 3..8).prop_map(|points| {
+                Shape::Polygon(convex_hull(
+                    points
+                        .into_iter()
+                        .map(|(x, y)| Vec2::new(x, y))
+                        .collect(),
+                ))
+            }),
+        ]
+    }
+
+    fn arb_transform() -> impl Strategy<Value = Transform> {
+        (-100.0_f32..100.0, -100.0_f32..100.0, 0.0_f32..2.0 * PI).prop_map(|(x, y, angle)| {
+            Transform::from_xyz(x, y, 0.0).with_rotation(Quat::from_rotation_z(angle))
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn distance_is_symmetric(
+            s1 in arb_shape(),
+            t1 in arb_transform(),
+            s2 in arb_shape(),
+            t2 in arb_transform(),
+        ) {
+            let d_12 = Shape::nearest_pass(&s1, &t1, &s2, &t2).distance();
+            let d_21 = Shape::nearest_pass(&s2, &t2, &s1, &t1).distance();
+            prop_assert!((d_12 - d_21).abs() <= 1e-3 * d_12.abs().max(1.0), "{} != {}", d_12, d_21);
+        }
+    }
+}
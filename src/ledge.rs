@@ -0,0 +1,227 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{
+    fighter::{
+        AirSpeed, FastFalling, FighterEventSet, FighterSet, FighterStateUpdate, JumpSpeed,
+        CROUCH_THRESHOLD,
+    },
+    fighter_state::{
+        FighterState, LEDGE_ATTACK_DURATION_FRAMES, LEDGE_GETUP_DURATION_FRAMES,
+        LEDGE_ROLL_DURATION_FRAMES,
+    },
+    input::Control,
+    physics::{Collider, Velocity},
+    utils::{Facing, FrameCount, FrameNumber, LeftRight},
+};
+
+// Grab box around the ledge, horizontal values are measured outward from the stage
+const LEDGE_GRAB_BOX_INWARD: f32 = 10.0;
+const LEDGE_GRAB_BOX_OUTWARD: f32 = 40.0;
+const LEDGE_GRAB_BOX_ABOVE: f32 = 10.0;
+const LEDGE_GRAB_BOX_BELOW: f32 = 80.0;
+// Where a hanging fighter's feet are relative to the ledge
+const LEDGE_HANG_OFFSET: Vec2 = Vec2::new(12.0, -70.0);
+const LEDGE_GETUP_DISTANCE: f32 = 30.0;
+const LEDGE_ROLL_DISTANCE: f32 = 90.0;
+const LEDGE_REGRAB_COOLDOWN_FRAMES: FrameNumber = 30;
+
+#[derive(Component)]
+pub struct Ledge {
+    // Side of the stage this ledge is on; fighters hang off of this side
+    pub side: LeftRight,
+}
+
+impl Ledge {
+    // Both ends of a floor are grabbable
+    pub fn at_endpoints(collider: &Collider, centre: &Vec3) -> [(Ledge, Vec2); 2] {
+        let (a, b) = collider.get_endpoints(centre);
+        let (left, right) = if a.x < b.x { (a, b) } else { (b, a) };
+        [
+            (
+                Ledge {
+                    side: LeftRight::Left,
+                },
+                left,
+            ),
+            (
+                Ledge {
+                    side: LeftRight::Right,
+                },
+                right,
+            ),
+        ]
+    }
+
+    fn grab_box_contains(&self, ledge: Vec2, position: Vec2) -> bool {
+        let outward = (position.x - ledge.x) * self.side.get_sign();
+        let above = position.y - ledge.y;
+        (-LEDGE_GRAB_BOX_INWARD..=LEDGE_GRAB_BOX_OUTWARD).contains(&outward)
+            && (-LEDGE_GRAB_BOX_BELOW..=LEDGE_GRAB_BOX_ABOVE).contains(&above)
+    }
+
+    fn hang_position(&self, ledge: Vec2) -> Vec2 {
+        ledge + Vec2::new(LEDGE_HANG_OFFSET.x * self.side.get_sign(), LEDGE_HANG_OFFSET.y)
+    }
+
+    fn stand_position(&self, ledge: Vec2, distance: f32) -> Vec2 {
+        ledge - Vec2::new(distance * self.side.get_sign(), 0.0)
+    }
+
+    // Climb up first, then move onto the stage
+    fn getup_position(&self, ledge: Vec2, distance: f32, progress: f32) -> Vec2 {
+        let hang = self.hang_position(ledge);
+        let stand = self.stand_position(ledge, distance);
+        Vec2::new(
+            hang.x + (stand.x - hang.x) * progress,
+            hang.y + (stand.y - hang.y) * (2.0 * progress).min(1.0),
+        )
+    }
+}
+
+#[derive(Component)]
+pub struct HoldingLedge(pub Entity);
+
+#[derive(Component)]
+pub struct LedgeGrabCooldown(pub FrameNumber);
+
+fn grab_ledge(
+    mut commands: Commands,
+    q_fighter: Query<
+        (Entity, &FighterState, &Transform, &Velocity, &Control),
+        Without<LedgeGrabCooldown>,
+    >,
+    q_ledge: Query<(Entity, &Ledge, &Transform)>,
+    q_holding: Query<&HoldingLedge>,
+    mut ev_state: EventWriter<FighterStateUpdate>,
+) {
+    let mut held: HashSet<Entity> = q_holding.iter().map(|h| h.0).collect();
+    for (fighter, state, transform, velocity, control) in q_fighter.iter() {
+        // Holding down lets the fighter fall past the ledge
+        if !state.can_grab_ledge() || velocity.0.y > 0.0 || control.stick.y < -CROUCH_THRESHOLD {
+            continue;
+        }
+        let position = transform.translation.xy();
+        let Some((ledge_entity, ..)) = q_ledge
+            .iter()
+            .filter(|(e, ..)| !held.contains(e))
+            .find(|(_, ledge, t)| ledge.grab_box_contains(t.translation.xy(), position))
+        else {
+            continue;
+        };
+        held.insert(ledge_entity);
+        commands
+            .entity(fighter)
+            .insert(HoldingLedge(ledge_entity))
+            .remove::<FastFalling>();
+        ev_state.send(FighterStateUpdate(fighter, FighterState::LedgeHang));
+    }
+}
+
+fn hold_ledge(
+    mut q_fighter: Query<(
+        &FighterState,
+        &FrameCount,
+        &HoldingLedge,
+        &mut Transform,
+        &mut Velocity,
+        &mut Facing,
+    )>,
+    q_ledge: Query<(&Ledge, &Transform), Without<HoldingLedge>>,
+) {
+    for (state, frame, holding, mut transform, mut velocity, mut facing) in q_fighter.iter_mut() {
+        let Ok((ledge, ledge_transform)) = q_ledge.get(holding.0) else {
+            continue;
+        };
+        let ledge_position = ledge_transform.translation.xy();
+        let progress = |duration: FrameNumber| frame.0 as f32 / duration as f32;
+        let position = match state {
+            FighterState::LedgeHang => ledge.hang_position(ledge_position),
+            FighterState::LedgeGetup => ledge.getup_position(
+                ledge_position,
+                LEDGE_GETUP_DISTANCE,
+                progress(LEDGE_GETUP_DURATION_FRAMES),
+            ),
+            FighterState::LedgeRoll => ledge.getup_position(
+                ledge_position,
+                LEDGE_ROLL_DISTANCE,
+                progress(LEDGE_ROLL_DURATION_FRAMES),
+            ),
+            FighterState::LedgeAttack => ledge.getup_position(
+                ledge_position,
+                LEDGE_GETUP_DISTANCE,
+                progress(LEDGE_ATTACK_DURATION_FRAMES),
+            ),
+            _ => continue,
+        };
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        velocity.0 = Vec2::ZERO;
+        facing.0 = ledge.side.flip();
+    }
+}
+
+fn release_ledge(mut commands: Commands, q: Query<(Entity, &FighterState), With<HoldingLedge>>) {
+    for (entity, state) in q.iter() {
+        if state.is_holding_ledge() {
+            continue;
+        }
+        commands
+            .entity(entity)
+            .remove::<HoldingLedge>()
+            .insert(LedgeGrabCooldown(LEDGE_REGRAB_COOLDOWN_FRAMES));
+    }
+}
+
+fn decrement_ledge_grab_cooldown(
+    mut commands: Commands,
+    mut q: Query<(Entity, &mut LedgeGrabCooldown)>,
+) {
+    for (entity, mut cooldown) in q.iter_mut() {
+        cooldown.0 = cooldown.0.saturating_sub(1);
+        if cooldown.0 == 0 {
+            commands
+                .entity(entity)
+                .remove::<LedgeGrabCooldown>();
+        }
+    }
+}
+
+fn apply_ledge_jump_speed(
+    mut query: Query<(
+        &FighterState,
+        &FrameCount,
+        &Facing,
+        &mut Velocity,
+        &JumpSpeed,
+        &AirSpeed,
+    )>,
+) {
+    for (state, frame, facing, mut velocity, jump_speed, air_speed) in query.iter_mut() {
+        if state != &FighterState::LedgeJump || frame.0 != 0 {
+            continue;
+        }
+        velocity.0 = Vec2::new(facing.0.get_sign() * air_speed.0, jump_speed.0);
+    }
+}
+
+pub struct LedgePlugin;
+
+impl Plugin for LedgePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                decrement_ledge_grab_cooldown,
+                release_ledge,
+                grab_ledge,
+                hold_ledge,
+                apply_ledge_jump_speed,
+            )
+                .chain()
+                .in_set(FighterEventSet::React)
+                .after(FighterSet),
+        );
+    }
+}
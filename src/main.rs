@@ -1,25 +1,31 @@
 #![feature(if_let_guard)]
 #![feature(let_chains)]
 #![feature(iter_map_windows)]
+#![cfg_attr(test, feature(test))]
 
 use bevy::{log::LogPlugin, prelude::*, render::view::RenderLayers, sprite::Anchor};
 use input::{Control, InputSet};
 use iyes_perf_ui::prelude::*;
 
+mod camera;
 mod fighter;
 mod fighter_state;
+mod hazard;
 mod hitbox;
 mod input;
+mod ledge;
 mod physics;
 mod projectile;
+mod stage;
 mod utils;
 mod view;
 
 use fighter::{
-    megaman::MegaMan, DashSpeed, FighterBundle, FighterEventSet, JumpSpeed, Percent, PlayerId,
-    RunSpeed, Traction, WalkSpeed, Weight,
+    megaman::MegaMan, AirAcceleration, AirSpeed, DashSpeed, FallSpeed, FighterBundle,
+    FighterEventSet, JumpSpeed, Percent, PlayerId, RunSpeed, Traction, WalkSpeed,
+    WallJumpSpeed, Weight,
 };
-use fighter_state::FighterStateTransition;
+use fighter_state::{FighterStateTransition, StateArmour};
 use physics::*;
 use utils::{DebugMode, Facing, FrameCount, FrameNumber, LeftRight, VisibleDuringDebug};
 use view::*;
@@ -49,6 +55,12 @@ fn main() {
             utils::DebugPlugin,
             utils::LifetimePlugin,
             projectile::ProjectilePlugin,
+            ledge::LedgePlugin,
+        ))
+        .add_plugins((
+            stage::StagePlugin,
+            hazard::HazardPlugin,
+            camera::CameraPlugin,
         ))
         .insert_resource(Time::<Fixed>::from_hz(FRAMES_PER_SECOND as f64))
         .add_systems(Startup, setup)
@@ -117,6 +129,7 @@ fn setup(
             },
             ..Default::default()
         },
+        camera::GameCamera::default(),
         RenderLayers::layer(0),
     ));
     commands
@@ -125,9 +138,10 @@ fn setup(
                 tag: PlayerId(0),
                 frame: FrameCount(0),
                 facing: Facing(LeftRight::Right),
-                velocity: Velocity(Vec2::new(5.0, 0.0)),
+                velocity: Velocity::default(),
                 state: fighter_state::FighterState::default(),
                 state_transition_properties: FighterStateTransition::default(),
+                armour: StateArmour::default(),
                 animation_indices: animation_indices.clone(),
                 animation_timer: view::AnimationTimer(animation_timer.clone()),
                 control: Control::default(),
@@ -139,29 +153,16 @@ fn setup(
                 dash_speed: DashSpeed(fighter::megaman::MEGAMAN_DASH_SPEED),
                 run_speed: RunSpeed(fighter::megaman::MEGAMAN_DASH_SPEED),
                 walk_speed: WalkSpeed(fighter::megaman::MEGAMAN_WALK_SPEED),
+                air_acceleration: AirAcceleration(fighter::megaman::MEGAMAN_AIR_ACCELERATION),
+                air_speed: AirSpeed(fighter::megaman::MEGAMAN_AIR_SPEED),
+                fall_speed: FallSpeed(fighter::megaman::MEGAMAN_FALL_SPEED),
+                wall_jump_speed: WallJumpSpeed(fighter::megaman::MEGAMAN_WALL_JUMP_SPEED),
+                pushbox: MegaMan::get_pushbox(),
             },
             sprite_sheet_bundle.clone(),
             MegaMan,
         ))
         .with_children(MegaMan::spawn_body_hitboxes);
-    commands.spawn((
-        SpriteBundle {
-            transform: Transform {
-                scale: Vec3::new(800.0, 1.0, 0.0),
-                translation: Vec3::new(0.0, -200.0, 0.0),
-                ..default()
-            },
-            sprite: Sprite {
-                color: Color::WHITE,
-                ..default()
-            },
-            ..default()
-        },
-        Collider {
-            normal: Vec2::new(0.0, 1.0),
-            breadth: 800.0,
-        },
-    ));
     commands.spawn((
         PerfUiCompleteBundle::default(),
         VisibleDuringDebug,
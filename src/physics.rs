@@ -1,4 +1,11 @@
 use bevy::{ecs::schedule::SystemSet, prelude::*};
+use itertools::Itertools;
+
+use crate::{
+    fighter::InHitstun,
+    hitbox::Shape,
+    utils::{FrameCount, FrameNumber, LeftRight},
+};
 
 #[derive(Component, Default)]
 pub struct Velocity(pub Vec2);
@@ -51,12 +58,32 @@ fn accelerate_towards(
 #[derive(Component)]
 pub struct Gravity(pub f32);
 
-fn accelerate_from_gravity(mut query: Query<(&mut Velocity, &Gravity)>) {
-    for (mut v, g) in &mut query {
-        v.0.y += g.0;
+// Terminal downward speed, only enforced against acceleration from gravity
+#[derive(Component)]
+pub struct MaxFallSpeed(pub f32);
+
+fn accelerate_from_gravity(mut query: Query<(&mut Velocity, &Gravity, Option<&MaxFallSpeed>)>) {
+    for (mut v, g, max_fall_speed) in &mut query {
+        let Some(MaxFallSpeed(max_fall_speed)) = max_fall_speed else {
+            v.0.y += g.0;
+            continue;
+        };
+        // Objects already falling faster (e.g. after a spike) keep their speed
+        if v.0.y > -max_fall_speed {
+            v.0.y = (v.0.y + g.0).max(-max_fall_speed);
+        }
     }
 }
 
+// How far behind a surface an object can be while still colliding with it
+const COLLISION_TOLERANCE: f32 = 0.01;
+// Grounded objects are pulled this far back onto their floor so they notice when it ends
+const GROUND_SNAP_DISTANCE: f32 = 1.0;
+// Floor endpoints closer than this are joined, so objects walk from one floor onto the other
+pub const FLOOR_JOIN_TOLERANCE: f32 = 0.5;
+// Maximum number of surfaces an object can slide between in one frame
+const MAX_COLLISIONS_PER_FRAME: usize = 4;
+
 #[derive(Component)]
 pub struct Collider {
     pub normal: Vec2,
@@ -64,7 +91,30 @@ pub struct Collider {
 }
 
 impl Collider {
-    fn get_pushback(&self, position: &Vec3, displacement: &Vec2, centre: &Vec3) -> Option<Vec2> {
+    // Surface between two endpoints, facing to the left of the direction from `a` to `b` (see GDD)
+    pub fn from_endpoints(a: Vec2, b: Vec2) -> Self {
+        let tangent = b - a;
+        Collider {
+            normal: Vec2::new(-tangent.y, tangent.x).normalize(),
+            breadth: tangent.length(),
+        }
+    }
+
+    // Endpoints in the same order as they were given to `from_endpoints`
+    pub fn get_endpoints(&self, centre: &Vec3) -> (Vec2, Vec2) {
+        let c = Vec2::new(centre.x, centre.y);
+        let half_tangent = Vec2::new(self.normal.y, -self.normal.x) * self.breadth * 0.5;
+        (c - half_tangent, c + half_tangent)
+    }
+
+    // Fraction of the displacement travelled before touching the surface, and the pushback needed
+    // to slide along it for the rest of the displacement (see GDD)
+    fn get_collision(
+        &self,
+        position: &Vec3,
+        displacement: &Vec2,
+        centre: &Vec3,
+    ) -> Option<(f32, Vec2)> {
         let p = Vec2::new(position.x, position.y);
         let c = Vec2::new(centre.x, centre.y);
         let denominator = self.normal.dot(*displacement);
@@ -73,9 +123,13 @@ impl Collider {
         if denominator >= 0.0 {
             return None;
         }
-        let numerator = self.normal.dot(c - p);
-        let t = numerator / denominator;
-        if t < 0.0 || t > 1.0 {
+        // Objects resting on a surface may end up very slightly behind it due to rounding
+        let distance = self.normal.dot(p - c);
+        if distance < -COLLISION_TOLERANCE {
+            return None;
+        }
+        let t = (-distance / denominator).max(0.0);
+        if t > 1.0 {
             return None;
         }
         let b_0 = p + t * *displacement;
@@ -84,60 +138,347 @@ impl Collider {
             return None;
         }
         let result = (t - 1.0) * displacement.dot(self.normal) * self.normal;
-        return Some(result);
+        Some((t, result))
+    }
+}
+
+// One-way surface which objects with `DropThrough` don't collide with
+#[derive(Component)]
+pub struct Platform;
+
+#[derive(Component)]
+pub struct DropThrough;
+
+// Route followed by a collider, as offsets from `origin` at each keyframe, sorted by frame.
+// The path holds its first offset until the first keyframe and loops back after the last one
+#[derive(Component)]
+pub struct KeyframedPath {
+    pub origin: Vec2,
+    pub keyframes: Vec<(FrameNumber, Vec2)>,
+}
+
+impl KeyframedPath {
+    pub fn position_at(&self, frame: FrameNumber) -> Vec2 {
+        let Some(&(period, last)) = self.keyframes.last() else {
+            return self.origin;
+        };
+        if period == 0 {
+            return self.origin + last;
+        }
+        let frame = frame % period;
+        let offset = self
+            .keyframes
+            .iter()
+            .tuple_windows()
+            .find(|((_, _), (end, _))| frame < *end)
+            .map_or(last, |((start, a), (end, b))| {
+                // Saturating so that early frames and unsorted keyframes can't underflow
+                let progress =
+                    frame.saturating_sub(*start) as f32 / end.saturating_sub(*start).max(1) as f32;
+                a.lerp(*b, progress)
+            });
+        self.origin + offset
+    }
+}
+
+// How far a moving collider travelled this frame
+#[derive(Component, Default)]
+pub struct ColliderDisplacement(pub Vec2);
+
+fn move_along_path(
+    mut q: Query<(
+        &KeyframedPath,
+        &FrameCount,
+        &mut Transform,
+        &mut ColliderDisplacement,
+    )>,
+) {
+    for (path, frame, mut transform, mut displacement) in q.iter_mut() {
+        let position = path.position_at(frame.0);
+        displacement.0 = position - transform.translation.xy();
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
+
+// Floor the object landed on during the last frame
+#[derive(Component)]
+pub struct StandingOn(pub Entity);
+
+// Grounded objects keep their horizontal speed and move along the floor's slope, unless they
+// are moving away from it (e.g. jumping)
+fn follow_ground(
+    mut q: Query<(&mut Velocity, &StandingOn), Without<Airborne>>,
+    q_collider: Query<&Collider>,
+) {
+    for (mut v, standing_on) in q.iter_mut() {
+        let Ok(collider) = q_collider.get(standing_on.0) else {
+            continue;
+        };
+        let normal = collider.normal;
+        if v.0.dot(normal) > 0.0 || normal.y <= 0.0 {
+            continue;
+        }
+        let tangent = Vec2::new(normal.y, -normal.x);
+        v.0 = Vec2::new(v.0.x, v.0.x * tangent.y / tangent.x);
     }
 }
 
 #[derive(Event)]
 pub struct Collision {
     pub entity: Entity,
+    pub collider: Entity,
     pub normal: Vec2,
+    // Which side of the object the wall is on, if the surface is a wall
+    pub wall_side: Option<LeftRight>,
+    // Velocity before the collision changed it
+    pub incoming_velocity: Vec2,
+    pub in_hitstun: bool,
+}
+
+fn get_wall_side(normal: &Vec2) -> Option<LeftRight> {
+    if normal.x.abs() < normal.y.abs() {
+        None
+    } else if normal.x > 0.0 {
+        Some(LeftRight::Left)
+    } else {
+        Some(LeftRight::Right)
+    }
 }
 
 #[derive(Component)]
 pub struct Airborne;
 
 fn apply_velocity(
-    mut objects: Query<(Entity, &mut Transform, &mut Velocity)>,
-    colliders: Query<(&Collider, &Transform), Without<Velocity>>,
+    mut objects: Query<(Entity, &mut Transform, &mut Velocity, Option<&StandingOn>)>,
+    colliders: Query<
+        (Entity, &Collider, &Transform, Option<&ColliderDisplacement>),
+        Without<Velocity>,
+    >,
+    q_drop_through: Query<(), With<DropThrough>>,
+    q_hitstun: Query<(), With<InHitstun>>,
+    q_platform: Query<(), With<Platform>>,
     mut ev_collision: EventWriter<Collision>,
     mut commands: Commands,
 ) {
-    for (entity, mut p, mut v) in &mut objects {
-        let pushback = displace_and_return_pushback(&mut p, &v.0, colliders.iter());
-        if (pushback.length()) == 0.0 {
-            if let Some(mut e) = commands.get_entity(entity) {
-                e.insert(Airborne);
+    for (entity, mut p, mut v, standing_on) in &mut objects {
+        let drop_through = q_drop_through.contains(entity);
+        let incoming_velocity = v.0;
+        let collider_displacement = |e: Entity| {
+            colliders
+                .get(e)
+                .ok()
+                .and_then(|(.., d)| d)
+                .map_or(Vec2::ZERO, |d| d.0)
+        };
+        // Objects standing on a moving floor are carried along with it
+        let carried = standing_on.map_or(Vec2::ZERO, |s| collider_displacement(s.0));
+        let solid_colliders: Vec<_> = colliders
+            .iter()
+            .filter(|(e, ..)| !(drop_through && q_platform.contains(*e)))
+            .map(|(e, collider, centre, d)| (e, collider, centre, d.map_or(Vec2::ZERO, |d| d.0)))
+            .collect();
+        let mut collisions =
+            displace_and_return_collisions(&mut p, &(v.0 + carried), &solid_colliders);
+        let landed = collisions
+            .iter()
+            .any(|(_, normal)| get_wall_side(normal).is_none() && normal.y > 0.0);
+        // Only the floor being stood on and floors joined to it are snapped to, a short distance,
+        // so walking over a slope crest or off of a ledge above a lower floor goes airborne
+        if !landed
+            && let Some(&(_, floor, floor_centre, _)) = standing_on
+                .and_then(|s| solid_colliders.iter().find(|(e, ..)| *e == s.0))
+            && v.0.dot(floor.normal) <= 0.0
+        {
+            let (a, b) = floor.get_endpoints(&floor_centre.translation);
+            let is_joined = |point: Vec2| {
+                point.distance(a) < FLOOR_JOIN_TOLERANCE || point.distance(b) < FLOOR_JOIN_TOLERANCE
+            };
+            let snap_colliders: Vec<_> = solid_colliders
+                .iter()
+                .filter(|(_, collider, centre, _)| {
+                    let (c, d) = collider.get_endpoints(&centre.translation);
+                    is_joined(c) || is_joined(d)
+                })
+                .map(|&(e, collider, centre, _)| (e, collider, centre, Vec2::ZERO))
+                .collect();
+            let before_snap = p.translation;
+            let snapped = displace_and_return_collisions(
+                &mut p,
+                &(-floor.normal * GROUND_SNAP_DISTANCE),
+                &snap_colliders,
+            );
+            if snapped.is_empty() {
+                p.translation = before_snap;
+            }
+            collisions.extend(snapped);
+        }
+        let mut floor = None;
+        for (collider, normal) in collisions {
+            // Stop moving into the surface, which also stops a collider that moves into the
+            // object from launching it
+            let normal_speed = v.0.dot(normal).min(0.0);
+            v.0 -= normal_speed * normal;
+            let wall_side = get_wall_side(&normal);
+            ev_collision.send(Collision {
+                entity,
+                collider,
+                normal,
+                wall_side,
+                incoming_velocity,
+                in_hitstun: q_hitstun.contains(entity),
+            });
+            // Only floors can ground an object, touching a wall or ceiling leaves it airborne
+            if wall_side.is_none() && normal.y > 0.0 {
+                floor = Some(collider);
             }
-            continue;
         }
-        let normal = pushback.normalize();
-        let modified_pushback = normal * normal.dot(pushback);
-        v.0 += modified_pushback;
-        ev_collision.send(Collision { entity, normal });
         if let Some(mut e) = commands.get_entity(entity) {
-            e.remove::<Airborne>();
+            match floor {
+                Some(collider) => e.remove::<Airborne>().insert(StandingOn(collider)),
+                None => e.insert(Airborne).remove::<StandingOn>(),
+            };
         }
     }
 }
 
-fn displace_and_return_pushback<'a>(
+// Moves the object along the displacement, sliding along every surface it hits on the way in the
+// order they're hit, so fast objects can't pass through surfaces and corners are resolved.
+// Colliders are tested where they are at the end of the frame. Moving colliders are handled
+// in their own frame of reference, so objects they move into are pushed along
+fn displace_and_return_collisions(
     position: &mut Transform,
     displacement: &Vec2,
-    colliders: impl Iterator<Item = (&'a Collider, &'a Transform)>,
-) -> Vec2 {
-    let pushback = colliders
-        .into_iter()
-        .filter_map(|(collider, centre)| {
-            collider.get_pushback(&position.translation, displacement, &centre.translation)
-        })
-        // .filter(|p| p.length() > 1.0)
-        .next()
-        .unwrap_or_default();
-    let net_displacement = *displacement + pushback;
-    position.translation.x += net_displacement.x;
-    position.translation.y += net_displacement.y;
-    return pushback;
+    colliders: &[(Entity, &Collider, &Transform, Vec2)],
+) -> Vec<(Entity, Vec2)> {
+    let mut collisions = Vec::new();
+    let mut remaining = *displacement;
+    for i in 0..MAX_COLLISIONS_PER_FRAME {
+        let nearest = colliders
+            .iter()
+            .filter_map(|(entity, collider, centre, collider_displacement)| {
+                // After the first collision the object is already moving relative to where
+                // colliders end up
+                let collider_displacement = if i == 0 {
+                    *collider_displacement
+                } else {
+                    Vec2::ZERO
+                };
+                let start = position.translation + collider_displacement.extend(0.0);
+                let relative_displacement = remaining - collider_displacement;
+                collider
+                    .get_collision(&start, &relative_displacement, &centre.translation)
+                    .filter(|(_, pushback)| pushback.length() > 0.0)
+                    .map(|(t, pushback)| (*entity, t, pushback, start, relative_displacement))
+            })
+            .min_by(|(_, t1, ..), (_, t2, ..)| t1.total_cmp(t2));
+        let Some((entity, t, pushback, start, relative_displacement)) = nearest else {
+            break;
+        };
+        collisions.push((entity, pushback.normalize()));
+        // Stop at the surface, then slide along it for the rest of the frame
+        let contact = start + (relative_displacement * t).extend(0.0);
+        position.translation.x = contact.x;
+        position.translation.y = contact.y;
+        remaining = relative_displacement * (1.0 - t) + pushback;
+    }
+    position.translation.x += remaining.x;
+    position.translation.y += remaining.y;
+    collisions
+}
+
+// Grounded objects with pushboxes can't stand inside each other
+#[derive(Component)]
+pub struct Pushbox {
+    pub shape: Shape,
+    pub offset: Vec2,
+}
+
+const MAX_PUSH_SPEED: f32 = 2.0;
+
+// Most floors walked across when looking for the end of the floor an object is standing on
+const MAX_JOINED_FLOORS: usize = 16;
+
+// How far an object standing on a floor can move in a direction before reaching a ledge. Floors
+// joined to the one it's standing on are walked across, so only real ledges stop it
+fn room_on_floor(
+    x: f32,
+    direction: f32,
+    standing_on: &StandingOn,
+    q_floor: &Query<(Entity, &Collider, &Transform), Without<Pushbox>>,
+) -> f32 {
+    // Endpoints ordered along the direction
+    let ordered_endpoints = |collider: &Collider, centre: &Transform| {
+        let (a, b) = collider.get_endpoints(&centre.translation);
+        if (b.x - a.x) * direction >= 0.0 {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    };
+    let Ok((mut floor, collider, centre)) = q_floor.get(standing_on.0) else {
+        return 0.0;
+    };
+    let (_, mut edge) = ordered_endpoints(collider, centre);
+    for _ in 0..MAX_JOINED_FLOORS {
+        let next = q_floor
+            .iter()
+            .filter(|(e, collider, _)| {
+                *e != floor && get_wall_side(&collider.normal).is_none() && collider.normal.y > 0.0
+            })
+            .find_map(|(e, collider, centre)| {
+                let (near, far) = ordered_endpoints(collider, centre);
+                (near.distance(edge) < FLOOR_JOIN_TOLERANCE && (far.x - edge.x) * direction > 0.0)
+                    .then_some((e, far))
+            });
+        let Some((next_floor, next_edge)) = next else {
+            break;
+        };
+        floor = next_floor;
+        edge = next_edge;
+    }
+    ((edge.x - x) * direction).max(0.0)
+}
+
+fn push_apart(
+    mut q: Query<(&mut Transform, &Pushbox, &StandingOn), Without<Airborne>>,
+    q_floor: Query<(Entity, &Collider, &Transform), Without<Pushbox>>,
+) {
+    let mut iter = q.iter_combinations_mut();
+    while let Some([(mut t1, pushbox_1, standing_on_1), (mut t2, pushbox_2, standing_on_2)]) =
+        iter.fetch_next()
+    {
+        let p1 = t1.translation.xy() + pushbox_1.offset;
+        let p2 = t2.translation.xy() + pushbox_2.offset;
+        let overlap = -Shape::nearest_pass(
+            &pushbox_1.shape,
+            &Transform::from_translation(p1.extend(0.0)),
+            &pushbox_2.shape,
+            &Transform::from_translation(p2.extend(0.0)),
+        )
+        .distance();
+        if overlap <= 0.0 {
+            continue;
+        }
+        // Fighters standing exactly on top of each other are separated in a consistent direction
+        let direction = if p2.x >= p1.x { 1.0 } else { -1.0 };
+        let push = (0.5 * overlap).min(MAX_PUSH_SPEED);
+        // Whoever is at a ledge stays put and the other is pushed further
+        let push_1 = push.min(room_on_floor(
+            t1.translation.x,
+            -direction,
+            standing_on_1,
+            &q_floor,
+        ));
+        let push_2 = (2.0 * push - push_1).min(MAX_PUSH_SPEED).min(room_on_floor(
+            t2.translation.x,
+            direction,
+            standing_on_2,
+            &q_floor,
+        ));
+        t1.translation.x -= push_1 * direction;
+        t2.translation.x += push_2 * direction;
+    }
 }
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
@@ -153,7 +494,10 @@ impl Plugin for PhysicsPlugin {
                 accelerate_towards,
                 add_velocity,
                 accelerate_from_gravity,
+                follow_ground,
+                move_along_path,
                 apply_velocity,
+                push_apart,
             )
                 .chain()
                 .in_set(PhysicsSet),
@@ -164,3 +508,202 @@ impl Plugin for PhysicsPlugin {
         .add_event::<Collision>();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    // Surface between two endpoints, positioned the way stages spawn them
+    fn spawn_surface(world: &mut World, a: Vec2, b: Vec2) -> Entity {
+        world
+            .spawn((
+                Collider::from_endpoints(a, b),
+                Transform::from_translation((0.5 * (a + b)).extend(0.0)),
+            ))
+            .id()
+    }
+
+    fn spawn_object(world: &mut World, position: Vec2, velocity: Vec2) -> Entity {
+        world
+            .spawn((
+                Transform::from_translation(position.extend(0.0)),
+                Velocity(velocity),
+            ))
+            .id()
+    }
+
+    // Runs a frame of movement, returning the object's position and the normals it collided with
+    fn move_object(world: &mut World, object: Entity) -> (Vec2, Vec<Vec2>) {
+        world.init_resource::<Events<Collision>>();
+        world.run_system_once(apply_velocity);
+        let normals = world
+            .resource_mut::<Events<Collision>>()
+            .drain()
+            .map(|collision| collision.normal)
+            .collect();
+        let position = world
+            .get::<Transform>(object)
+            .unwrap()
+            .translation
+            .xy();
+        (position, normals)
+    }
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < 0.1, "{a} is not near {b}");
+    }
+
+    #[test]
+    fn corner_stops_both_ways() {
+        let mut world = World::new();
+        let floor = spawn_surface(&mut world, Vec2::new(-100.0, 0.0), Vec2::new(100.0, 0.0));
+        spawn_surface(&mut world, Vec2::new(50.0, -100.0), Vec2::new(50.0, 100.0));
+        let object = spawn_object(&mut world, Vec2::new(40.0, 5.0), Vec2::new(20.0, -10.0));
+        let (position, normals) = move_object(&mut world, object);
+        assert_near(position, Vec2::new(50.0, 0.0));
+        assert!(normals.contains(&Vec2::Y));
+        assert!(normals.contains(&Vec2::NEG_X));
+        assert_near(world.get::<Velocity>(object).unwrap().0, Vec2::ZERO);
+        assert_eq!(world.get::<StandingOn>(object).unwrap().0, floor);
+    }
+
+    #[test]
+    fn ceiling_stops_rising_objects() {
+        let mut world = World::new();
+        spawn_surface(&mut world, Vec2::new(100.0, 50.0), Vec2::new(-100.0, 50.0));
+        let object = spawn_object(&mut world, Vec2::new(0.0, 40.0), Vec2::new(5.0, 20.0));
+        let (position, normals) = move_object(&mut world, object);
+        assert_near(position, Vec2::new(5.0, 50.0));
+        assert_eq!(normals, vec![Vec2::NEG_Y]);
+        assert_near(world.get::<Velocity>(object).unwrap().0, Vec2::new(5.0, 0.0));
+        assert!(world.entity(object).contains::<Airborne>());
+    }
+
+    #[test]
+    fn fast_objects_do_not_pass_through_thin_platforms() {
+        let mut world = World::new();
+        let platform = spawn_surface(&mut world, Vec2::new(-100.0, 0.0), Vec2::new(100.0, 0.0));
+        world
+            .entity_mut(platform)
+            .insert(Platform);
+        let object = spawn_object(&mut world, Vec2::new(0.0, 50.0), Vec2::new(0.0, -500.0));
+        let (position, _) = move_object(&mut world, object);
+        assert_near(position, Vec2::ZERO);
+        assert_eq!(world.get::<StandingOn>(object).unwrap().0, platform);
+    }
+
+    // Whether an object walking right off of the end of a flat floor is still grounded, with
+    // another floor next to it
+    fn grounded_after_walking_onto(next_floor: (Vec2, Vec2)) -> bool {
+        let mut world = World::new();
+        let floor = spawn_surface(&mut world, Vec2::new(-100.0, 0.0), Vec2::ZERO);
+        spawn_surface(&mut world, next_floor.0, next_floor.1);
+        let object = spawn_object(&mut world, Vec2::new(-1.0, 0.0), Vec2::new(5.0, 0.0));
+        world
+            .entity_mut(object)
+            .insert(StandingOn(floor));
+        move_object(&mut world, object);
+        !world.entity(object).contains::<Airborne>()
+    }
+
+    #[test]
+    fn walking_along_a_floor_stays_grounded() {
+        assert!(grounded_after_walking_onto((
+            Vec2::ZERO,
+            Vec2::new(100.0, 0.0)
+        )));
+    }
+
+    #[test]
+    fn walking_off_a_slope_crest_goes_airborne() {
+        assert!(!grounded_after_walking_onto((
+            Vec2::ZERO,
+            Vec2::new(100.0, -50.0)
+        )));
+    }
+
+    #[test]
+    fn walking_off_a_ledge_does_not_snap_to_a_lower_floor() {
+        assert!(!grounded_after_walking_onto((
+            Vec2::new(0.0, -3.0),
+            Vec2::new(100.0, -3.0)
+        )));
+    }
+
+    const PUSHBOX_RADIUS: f32 = 10.0;
+
+    // Positions of two fighters standing on the given floors after being pushed apart
+    fn positions_after_push(fighters: [(f32, Entity); 2], world: &mut World) -> [f32; 2] {
+        let entities = fighters.map(|(x, floor)| {
+            world
+                .spawn((
+                    Transform::from_xyz(x, 0.0, 0.0),
+                    Pushbox {
+                        shape: Shape::Circle(PUSHBOX_RADIUS),
+                        offset: Vec2::ZERO,
+                    },
+                    StandingOn(floor),
+                ))
+                .id()
+        });
+        world.run_system_once(push_apart);
+        entities.map(|e| world.get::<Transform>(e).unwrap().translation.x)
+    }
+
+    #[test]
+    fn overlapping_fighters_separate_symmetrically() {
+        let mut world = World::new();
+        let floor = spawn_surface(&mut world, Vec2::new(-100.0, 0.0), Vec2::new(100.0, 0.0));
+        assert_eq!(
+            positions_after_push([(-2.0, floor), (2.0, floor)], &mut world),
+            [-2.0 - MAX_PUSH_SPEED, 2.0 + MAX_PUSH_SPEED]
+        );
+    }
+
+    #[test]
+    fn fighter_at_a_ledge_stays_put() {
+        let mut world = World::new();
+        let floor = spawn_surface(&mut world, Vec2::new(-100.0, 0.0), Vec2::new(100.0, 0.0));
+        // Overlapping by 2, so the other fighter is pushed the whole way
+        assert_eq!(
+            positions_after_push([(-100.0, floor), (-82.0, floor)], &mut world),
+            [-100.0, -80.0]
+        );
+    }
+
+    #[test]
+    fn push_crosses_floor_joints() {
+        let mut world = World::new();
+        let left = spawn_surface(&mut world, Vec2::new(-100.0, 0.0), Vec2::ZERO);
+        spawn_surface(&mut world, Vec2::ZERO, Vec2::new(100.0, 0.0));
+        assert_eq!(
+            positions_after_push([(-3.0, left), (-1.0, left)], &mut world),
+            [-3.0 - MAX_PUSH_SPEED, -1.0 + MAX_PUSH_SPEED]
+        );
+    }
+
+    #[test]
+    fn path_holds_until_first_keyframe() {
+        let path = KeyframedPath {
+            origin: Vec2::new(0.0, 100.0),
+            keyframes: vec![(10, Vec2::ZERO), (20, Vec2::new(10.0, 0.0))],
+        };
+        assert_eq!(path.position_at(5), Vec2::new(0.0, 100.0));
+        assert_eq!(path.position_at(15), Vec2::new(5.0, 100.0));
+        // Loops back to before the first keyframe
+        assert_eq!(path.position_at(25), Vec2::new(0.0, 100.0));
+    }
+
+    #[test]
+    fn unsorted_path_does_not_underflow() {
+        let path = KeyframedPath {
+            origin: Vec2::ZERO,
+            keyframes: vec![(30, Vec2::X), (10, Vec2::Y), (40, Vec2::ZERO)],
+        };
+        for frame in 0..40 {
+            assert!(path.position_at(frame).is_finite());
+        }
+    }
+}
@@ -0,0 +1,390 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    ecs::system::EntityCommands,
+    prelude::*,
+};
+use serde::Deserialize;
+
+use crate::{
+    fighter::{FighterEventSet, FighterStateUpdate, Percent, PlayerId},
+    fighter_state::FighterState,
+    hazard::{spawn_hazard, HazardDescription, HazardsEnabled},
+    ledge::Ledge,
+    physics::{
+        Collider, ColliderDisplacement, KeyframedPath, Platform, Velocity, FLOOR_JOIN_TOLERANCE,
+    },
+    utils::{FrameCount, FrameNumber},
+};
+
+const DEFAULT_STAGE: &str = "stages/battlefield.stage.ron";
+
+// Surface between two endpoints, facing to the left of the direction from `a` to `b` (see GDD)
+#[derive(Deserialize)]
+pub struct Surface {
+    pub a: Vec2,
+    pub b: Vec2,
+    // Keyframes of (frame, offset) for surfaces that move, see `KeyframedPath`
+    #[serde(default)]
+    pub path: Vec<(FrameNumber, Vec2)>,
+}
+
+#[derive(Deserialize)]
+pub struct BackgroundLayer {
+    // Path within the assets folder; a plain rectangle is drawn without one
+    #[serde(default)]
+    pub image: Option<String>,
+    #[serde(default = "default_layer_colour")]
+    pub colour: [f32; 4],
+    pub position: Vec2,
+    pub size: Vec2,
+    // Negative depths are drawn behind the stage
+    pub depth: f32,
+}
+
+fn default_layer_colour() -> [f32; 4] {
+    [1.0, 1.0, 1.0, 1.0]
+}
+
+// Stage layout as read from a `.stage.ron` file in `assets/stages`
+#[derive(Asset, TypePath, Deserialize)]
+pub struct StageDescription {
+    pub name: String,
+    pub floors: Vec<Surface>,
+    pub walls: Vec<Surface>,
+    // One-way platforms
+    pub platforms: Vec<Surface>,
+    // Indexed by player
+    pub spawn_points: Vec<Vec2>,
+    // Where KO'd fighters come back, indexed by player
+    pub respawn_points: Vec<Vec2>,
+    // Fighters leaving this area are KO'd
+    pub blast_zone: Rect,
+    // The camera never shows anything outside of this area
+    pub camera_bounds: Rect,
+    #[serde(default)]
+    pub background_layers: Vec<BackgroundLayer>,
+    #[serde(default)]
+    pub hazards: Vec<HazardDescription>,
+}
+
+impl StageDescription {
+    pub fn spawn_point(&self, player: &PlayerId) -> Option<Vec2> {
+        point_for_player(&self.spawn_points, player)
+    }
+
+    pub fn respawn_point(&self, player: &PlayerId) -> Option<Vec2> {
+        point_for_player(&self.respawn_points, player)
+    }
+
+    // Ends of the floor, i.e. floor endpoints which aren't shared with another floor
+    pub fn ledges(&self) -> Vec<(Ledge, Vec2)> {
+        let endpoints = self
+            .floors
+            .iter()
+            .flat_map(|floor| [floor.a, floor.b])
+            .collect::<Vec<_>>();
+        self.floors
+            .iter()
+            // Ledges don't follow moving floors
+            .filter(|floor| floor.path.is_empty())
+            .flat_map(|floor| {
+                let centre = (0.5 * (floor.a + floor.b)).extend(0.0);
+                Ledge::at_endpoints(&Collider::from_endpoints(floor.a, floor.b), &centre)
+            })
+            .filter(|(_, position)| {
+                endpoints
+                    .iter()
+                    .filter(|endpoint| endpoint.distance(*position) < FLOOR_JOIN_TOLERANCE)
+                    .count()
+                    == 1
+            })
+            .collect()
+    }
+}
+
+// Players beyond the number of points share them
+fn point_for_player(points: &[Vec2], player: &PlayerId) -> Option<Vec2> {
+    if points.is_empty() {
+        return None;
+    }
+    Some(points[player.0 % points.len()])
+}
+
+#[derive(Default)]
+struct StageLoader;
+
+impl AssetLoader for StageLoader {
+    type Asset = StageDescription;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<StageDescription, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes::<StageDescription>(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["stage.ron"]
+    }
+}
+
+// Path of the stage to play on, relative to the assets folder
+#[derive(Resource)]
+pub struct SelectedStage(pub String);
+
+impl Default for SelectedStage {
+    // The first command line argument that isn't a flag chooses the stage
+    fn default() -> Self {
+        SelectedStage(
+            std::env::args()
+                .skip(1)
+                .find(|arg| !arg.starts_with("--"))
+                .unwrap_or(DEFAULT_STAGE.to_string()),
+        )
+    }
+}
+
+// The stage currently being played on; set once the stage's entities are spawned
+#[derive(Resource)]
+pub struct CurrentStage {
+    pub handle: Handle<StageDescription>,
+    pub spawned: bool,
+}
+
+#[derive(Component)]
+pub struct StageElement;
+
+fn load_stage(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    selected: Res<SelectedStage>,
+) {
+    commands.insert_resource(CurrentStage {
+        handle: asset_server.load(selected.0.clone()),
+        spawned: false,
+    });
+}
+
+fn spawn_stage(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    stages: Res<Assets<StageDescription>>,
+    mut current: ResMut<CurrentStage>,
+    hazards_enabled: Res<HazardsEnabled>,
+    mut q_fighter: Query<(&PlayerId, &mut Transform, &mut Velocity)>,
+) {
+    if current.spawned {
+        return;
+    }
+    let Some(stage) = stages.get(&current.handle) else {
+        return;
+    };
+    current.spawned = true;
+    info!("Loading stage {}", stage.name);
+    for surface in stage.floors.iter().chain(stage.walls.iter()) {
+        spawn_surface(&mut commands, surface);
+    }
+    for surface in stage.platforms.iter() {
+        spawn_surface(&mut commands, surface).insert(Platform);
+    }
+    for (ledge, position) in stage.ledges() {
+        commands.spawn((
+            ledge,
+            TransformBundle::from_transform(Transform::from_translation(position.extend(0.0))),
+            StageElement,
+        ));
+    }
+    if hazards_enabled.0 {
+        for hazard in stage.hazards.iter() {
+            let entity = spawn_hazard(&mut commands, hazard);
+            commands.entity(entity).insert(StageElement);
+        }
+    }
+    for layer in stage.background_layers.iter() {
+        let [red, green, blue, alpha] = layer.colour;
+        commands.spawn((
+            SpriteBundle {
+                texture: layer
+                    .image
+                    .as_ref()
+                    .map_or(default(), |image| asset_server.load(image.clone())),
+                sprite: Sprite {
+                    color: Color::linear_rgba(red, green, blue, alpha),
+                    custom_size: Some(layer.size),
+                    ..default()
+                },
+                transform: Transform::from_translation(layer.position.extend(layer.depth)),
+                ..default()
+            },
+            StageElement,
+        ));
+    }
+    for (player, mut transform, mut velocity) in q_fighter.iter_mut() {
+        let Some(spawn_point) = stage.spawn_point(player) else {
+            continue;
+        };
+        transform.translation.x = spawn_point.x;
+        transform.translation.y = spawn_point.y;
+        velocity.0 = Vec2::ZERO;
+    }
+}
+
+// Fighters leaving the blast zone are KO'd and come back at their respawn point
+fn ko_outside_blast_zone(
+    current: Option<Res<CurrentStage>>,
+    stages: Res<Assets<StageDescription>>,
+    mut q_fighter: Query<(Entity, &PlayerId, &mut Transform, &mut Velocity, &mut Percent)>,
+    mut ev_state: EventWriter<FighterStateUpdate>,
+) {
+    let Some(stage) = current.and_then(|current| stages.get(&current.handle)) else {
+        return;
+    };
+    for (entity, player, mut transform, mut velocity, mut percent) in q_fighter.iter_mut() {
+        if stage
+            .blast_zone
+            .contains(transform.translation.xy())
+        {
+            continue;
+        }
+        info!("Player {} was KO'd", player.0);
+        let respawn_point = stage
+            .respawn_point(player)
+            .unwrap_or_default();
+        transform.translation.x = respawn_point.x;
+        transform.translation.y = respawn_point.y;
+        velocity.0 = Vec2::ZERO;
+        *percent = Percent::default();
+        ev_state.send(FighterStateUpdate(entity, FighterState::IdleAirborne));
+    }
+}
+
+fn spawn_surface<'a>(commands: &'a mut Commands, surface: &Surface) -> EntityCommands<'a> {
+    let (a, b) = (surface.a, surface.b);
+    let tangent = b - a;
+    let centre = 0.5 * (a + b);
+    let mut entity = commands.spawn((
+        SpriteBundle {
+            transform: Transform {
+                scale: Vec3::new(tangent.length(), 1.0, 0.0),
+                rotation: Quat::from_rotation_z(tangent.to_angle()),
+                translation: centre.extend(0.0),
+            },
+            sprite: Sprite {
+                color: Color::WHITE,
+                ..default()
+            },
+            ..default()
+        },
+        Collider::from_endpoints(a, b),
+        StageElement,
+    ));
+    if !surface.path.is_empty() {
+        entity.insert((
+            KeyframedPath {
+                origin: centre,
+                keyframes: surface.path.clone(),
+            },
+            FrameCount(0),
+            ColliderDisplacement::default(),
+        ));
+    }
+    entity
+}
+
+pub struct StagePlugin;
+
+impl Plugin for StagePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<StageDescription>()
+            .init_asset_loader::<StageLoader>()
+            .init_resource::<SelectedStage>()
+            .add_systems(Startup, load_stage)
+            .add_systems(Update, spawn_stage)
+            .add_systems(
+                FixedUpdate,
+                ko_outside_blast_zone.in_set(FighterEventSet::Act),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    const HILLS: &str = "(
+        name: \"Test\",
+        floors: [
+            (a: (-200.0, 0.0), b: (0.0, 50.0)),
+            (a: (0.0, 50.0), b: (200.0, 0.0)),
+        ],
+        walls: [],
+        platforms: [],
+        spawn_points: [(0.0, 50.0)],
+        respawn_points: [(-50.0, 150.0), (50.0, 150.0)],
+        blast_zone: (min: (-500.0, -500.0), max: (500.0, 500.0)),
+        camera_bounds: (min: (-400.0, -400.0), max: (400.0, 400.0)),
+    )";
+
+    fn hills() -> StageDescription {
+        ron::de::from_str(HILLS).unwrap()
+    }
+
+    #[test]
+    fn joined_floor_endpoints_are_not_ledges() {
+        let mut positions = hills()
+            .ledges()
+            .into_iter()
+            .map(|(_, position)| position.x)
+            .collect::<Vec<_>>();
+        positions.sort_by(f32::total_cmp);
+        assert_eq!(positions, vec![-200.0, 200.0]);
+    }
+
+    // Position of a fighter after a frame at the given position, and whether they were KO'd
+    fn position_after_frame(position: Vec2) -> (Vec2, bool) {
+        let mut world = World::new();
+        world.init_resource::<Events<FighterStateUpdate>>();
+        let mut stages = Assets::<StageDescription>::default();
+        let handle = stages.add(hills());
+        world.insert_resource(stages);
+        world.insert_resource(CurrentStage {
+            handle,
+            spawned: true,
+        });
+        let fighter = world
+            .spawn((
+                PlayerId(1),
+                Transform::from_translation(position.extend(0.0)),
+                Velocity(Vec2::X),
+                Percent::default(),
+            ))
+            .id();
+        world.run_system_once(ko_outside_blast_zone);
+        let ko = !world
+            .resource::<Events<FighterStateUpdate>>()
+            .is_empty();
+        (world.get::<Transform>(fighter).unwrap().translation.xy(), ko)
+    }
+
+    #[test]
+    fn fighters_inside_the_blast_zone_stay() {
+        let position = Vec2::new(400.0, -300.0);
+        assert_eq!(position_after_frame(position), (position, false));
+    }
+
+    #[test]
+    fn fighters_leaving_the_blast_zone_respawn() {
+        assert_eq!(
+            position_after_frame(Vec2::new(0.0, -600.0)),
+            (Vec2::new(50.0, 150.0), true)
+        );
+    }
+}
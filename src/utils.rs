@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use serde::Deserialize;
 
 use crate::fighter::FighterEventSet;
 
@@ -7,7 +8,7 @@ pub type FrameNumber = u32;
 #[derive(Component)]
 pub struct FrameCount(pub FrameNumber);
 
-#[derive(PartialEq, Eq, Default, Clone, Copy)]
+#[derive(PartialEq, Eq, Default, Clone, Copy, Debug, Deserialize)]
 pub enum LeftRight {
     Left,
     #[default]
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::fighter::Intangible;
+use crate::fighter::{Intangible, Invincible};
 use crate::utils::{Facing, FrameCount, LeftRight};
 
 #[derive(Component, Clone, Debug, PartialEq, Eq)]
@@ -94,10 +94,15 @@ fn align_sprites_with_facing(mut query: Query<(&Facing, &mut Transform)>) {
     }
 }
 
-fn update_intangibility_flash(mut query: Query<(&mut Sprite, &FrameCount, Option<&Intangible>)>) {
-    for (mut sprite, frame, maybe_intangible) in query.iter_mut() {
-        if maybe_intangible.is_some() && (frame.0 / 3) % 2 == 0 {
+fn update_intangibility_flash(
+    mut query: Query<(&mut Sprite, &FrameCount, Has<Intangible>, Has<Invincible>)>,
+) {
+    for (mut sprite, frame, intangible, invincible) in query.iter_mut() {
+        let flashing = (frame.0 / 3) % 2 == 0;
+        if intangible && flashing {
             sprite.color = Color::hsl(0.0, 0.0, 1.75);
+        } else if invincible && flashing {
+            sprite.color = Color::hsl(50.0, 1.0, 0.75);
         } else {
             sprite.color = Color::WHITE;
         }